@@ -0,0 +1,202 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+use tokio::sync::watch;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TokenState {
+    Running,
+    Cancelled,
+    /// The install is writing the launcher profile and can't be stopped anymore.
+    Committed,
+}
+
+#[derive(Clone)]
+pub struct CancellationToken(Arc<watch::Sender<TokenState>>);
+
+impl CancellationToken {
+    fn new() -> Self {
+        Self(Arc::new(watch::channel(TokenState::Running).0))
+    }
+
+    fn transition(&self, to: TokenState) -> bool {
+        self.0.send_if_modified(|state| {
+            if *state == TokenState::Running {
+                *state = to;
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Returns whether the install was still cancellable.
+    pub fn cancel(&self) -> bool {
+        self.transition(TokenState::Cancelled)
+    }
+
+    /// Marks the point of no return. Returns `false` if the install was cancelled before.
+    pub fn commit(&self) -> bool {
+        self.transition(TokenState::Committed)
+    }
+
+    /// Resolves once the install is cancelled; never resolves after [`Self::commit`].
+    pub async fn cancelled(&self) {
+        let mut receiver = self.0.subscribe();
+        if receiver
+            .wait_for(|state| *state == TokenState::Cancelled)
+            .await
+            .is_err()
+        {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Tauri state holding the token of the install that is currently running.
+#[derive(Default)]
+pub struct ActiveInstall(Mutex<Option<CancellationToken>>);
+
+impl ActiveInstall {
+    pub fn begin(&self) -> Option<CancellationToken> {
+        let mut active = self.0.lock().unwrap();
+        if active.is_some() {
+            return None;
+        }
+        let token = CancellationToken::new();
+        active.replace(token.clone());
+        Some(token)
+    }
+
+    pub fn end(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    pub fn cancel(&self) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(CancellationToken::cancel)
+    }
+}
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Install was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Directory in the profile where files the install replaces are kept until
+/// it commits.
+const BACKUP_DIR: &str = ".paigaldaja_backup";
+
+/// Records what an install changed in the profile directory, so a cancelled
+/// or failed install can be undone.
+#[derive(Default)]
+pub struct InstallJournal {
+    profile_dir: OnceLock<PathBuf>,
+    committed: AtomicBool,
+    written_files: Mutex<Vec<PathBuf>>,
+    set_aside: Mutex<Vec<(PathBuf, PathBuf)>>,
+    disabled_mods: Mutex<Vec<(PathBuf, PathBuf)>>,
+}
+
+impl InstallJournal {
+    pub fn set_profile_dir(&self, path: &Path) {
+        let _ = self.profile_dir.set(path.to_owned());
+    }
+
+    pub fn wrote(&self, path: &Path) {
+        self.written_files.lock().unwrap().push(path.to_owned());
+    }
+
+    /// Moves an existing file out of the way before the install removes or
+    /// overwrites it, so [`Self::roll_back`] can put it back. Does nothing if
+    /// the file doesn't exist or was written by this install.
+    pub async fn set_aside(&self, path: &Path) -> std::io::Result<()> {
+        let Some(profile_dir) = self.profile_dir.get() else {
+            return Ok(());
+        };
+        if self.written_files.lock().unwrap().iter().any(|p| p == path)
+            || tokio::fs::symlink_metadata(path).await.is_err()
+        {
+            return Ok(());
+        }
+        let Ok(relative) = path.strip_prefix(profile_dir) else {
+            return Ok(());
+        };
+        let backup = profile_dir.join(BACKUP_DIR).join(relative);
+        if let Some(parent) = backup.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(path, &backup).await?;
+        self.set_aside
+            .lock()
+            .unwrap()
+            .push((path.to_owned(), backup));
+        Ok(())
+    }
+
+    pub fn disabled(&self, from: &Path, to: &Path) {
+        self.disabled_mods
+            .lock()
+            .unwrap()
+            .push((from.to_owned(), to.to_owned()));
+    }
+
+    /// Drops the files that were set aside once the launcher profile and the
+    /// metadata are written; the install can't be rolled back afterwards.
+    pub async fn commit(&self) {
+        self.committed.store(true, Ordering::Relaxed);
+        if let Some(profile_dir) = self.profile_dir.get() {
+            let _ = tokio::fs::remove_dir_all(profile_dir.join(BACKUP_DIR)).await;
+        }
+    }
+
+    /// Removes everything the install wrote, puts back the files it removed or
+    /// overwrote and re-enables the mods it disabled, restoring the previous
+    /// install. Does nothing once committed. Partial downloads are kept, so a
+    /// failed install can resume them.
+    pub async fn roll_back(&self) {
+        let Some(profile_dir) = self.profile_dir.get() else {
+            return;
+        };
+        if self.committed.load(Ordering::Relaxed) {
+            return;
+        }
+        let written_files = std::mem::take(&mut *self.written_files.lock().unwrap());
+        for file in written_files {
+            let _ = tokio::fs::remove_file(file).await;
+        }
+        let set_aside = std::mem::take(&mut *self.set_aside.lock().unwrap());
+        for (original, backup) in set_aside {
+            if let Some(parent) = original.parent() {
+                let _ = tokio::fs::create_dir_all(parent).await;
+            }
+            let _ = tokio::fs::rename(backup, original).await;
+        }
+        let disabled_mods = std::mem::take(&mut *self.disabled_mods.lock().unwrap());
+        for (from, to) in disabled_mods {
+            let _ = tokio::fs::rename(to, from).await;
+        }
+        let _ = tokio::fs::remove_dir_all(profile_dir.join(BACKUP_DIR)).await;
+    }
+
+    /// Removes the partial downloads of a cancelled install.
+    pub async fn discard_partial_downloads(&self) {
+        if let Some(profile_dir) = self.profile_dir.get() {
+            let _ = tokio::fs::remove_dir_all(profile_dir.join(".paigaldaja_staging")).await;
+        }
+    }
+}
@@ -1,3 +1,13 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::{
+    net::{NetworkConfig, RetryPolicy},
+    trust::SignaturePolicy,
+};
+
 pub const DOWNLOADS_DOMAIN_WHITELIST: &[&str] = &[
     "cdn.modrinth.com",
     "github.com",
@@ -6,3 +16,271 @@ pub const DOWNLOADS_DOMAIN_WHITELIST: &[&str] = &[
 ];
 
 pub const PACK_DOMAIN_WHITELIST: &[&str] = &["cdn.modrinth.com"];
+
+/// Hosts the Java runtime and Minecraft version metadata are fetched from.
+pub const MOJANG_DOMAIN_WHITELIST: &[&str] = &[
+    "piston-meta.mojang.com",
+    "piston-data.mojang.com",
+    "launchermeta.mojang.com",
+];
+
+pub const MODRINTH_API: &str = "https://api.modrinth.com/v2";
+
+// Debug builds accept unsigned packs (with a warning) so internal test packs can be installed;
+// release builds always require a signature or a pinned digest below.
+pub const SIGNATURE_POLICY: SignaturePolicy = if cfg!(debug_assertions) {
+    SignaturePolicy::WarnIfMissing
+} else {
+    SignaturePolicy::Require
+};
+
+// Lowercase hex SHA-256 digests of .mrpack files that are trusted without a signature.
+pub const TRUSTED_PACK_SHA256: &[&str] = &[];
+
+/// Settings read at startup from an admin-controlled JSON file. Any field left
+/// out of the file keeps the compiled default above.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Config {
+    pub downloads_domains: Vec<DomainRule>,
+    pub pack_domains: Vec<DomainRule>,
+    pub mojang_domains: Vec<DomainRule>,
+    pub mirrors: Vec<MirrorRule>,
+    pub network: NetworkConfig,
+    pub retry: RetryPolicy,
+    /// Base URL of the Modrinth API used to look for updates.
+    pub modrinth_api: String,
+    /// Why the config file couldn't be loaded, if it couldn't.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            downloads_domains: DOWNLOADS_DOMAIN_WHITELIST
+                .iter()
+                .map(|&domain| DomainRule::new(domain))
+                .collect(),
+            pack_domains: PACK_DOMAIN_WHITELIST
+                .iter()
+                .map(|&domain| DomainRule::new(domain))
+                .collect(),
+            mojang_domains: MOJANG_DOMAIN_WHITELIST
+                .iter()
+                .map(|&domain| DomainRule::new(domain))
+                .collect(),
+            mirrors: vec![],
+            network: NetworkConfig::default(),
+            retry: RetryPolicy::default(),
+            modrinth_api: MODRINTH_API.to_string(),
+            load_error: None,
+        }
+    }
+}
+
+impl Config {
+    /// Fails if the config file was invalid, so nothing is installed without
+    /// the restrictions an administrator set up.
+    pub fn ensure_loaded(&self) -> anyhow::Result<()> {
+        match &self.load_error {
+            Some(e) => Err(anyhow::anyhow!("{e}")),
+            None => Ok(()),
+        }
+    }
+
+    /// Expands a list of download URLs into the order they should be tried in:
+    /// every mirrored URL first, then the original URLs as a fallback.
+    pub fn with_mirrors(&self, urls: &[String]) -> Vec<String> {
+        let mut candidates = vec![];
+        for url in urls {
+            for mirror in &self.mirrors {
+                if let Some(rest) = url.strip_prefix(&mirror.from) {
+                    candidates.push(format!("{}{}", mirror.to, rest));
+                }
+            }
+        }
+        candidates.extend(urls.iter().cloned());
+        let mut seen = std::collections::HashSet::new();
+        candidates.retain(|url| seen.insert(url.clone()));
+        candidates
+    }
+}
+
+/// Rewrites URLs starting with `from` to start with `to` instead, e.g.
+/// `https://cdn.modrinth.com/` to `https://mirror.internal/modrinth/`. The
+/// rewritten URL still has to pass the domain allowlists.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorRule {
+    pub from: String,
+    pub to: String,
+}
+
+/// An allowlist entry. `*.example.com` matches any subdomain of `example.com`
+/// but not `example.com` itself. Written either as a bare domain string or as
+/// `{ "domain": ..., "schemes": [...] }`; only HTTPS is allowed by default, and
+/// plain HTTP URLs are upgraded unless `http` is listed explicitly.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(from = "DomainRuleRepr")]
+pub struct DomainRule {
+    pub domain: String,
+    pub schemes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DomainRuleRepr {
+    Domain(String),
+    Rule {
+        domain: String,
+        schemes: Vec<String>,
+    },
+}
+
+impl From<DomainRuleRepr> for DomainRule {
+    fn from(repr: DomainRuleRepr) -> Self {
+        match repr {
+            DomainRuleRepr::Domain(domain) => DomainRule::new(&domain),
+            DomainRuleRepr::Rule { domain, schemes } => DomainRule {
+                domain: domain.to_ascii_lowercase(),
+                schemes: schemes
+                    .into_iter()
+                    .map(|scheme| scheme.to_ascii_lowercase())
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl DomainRule {
+    fn new(domain: &str) -> Self {
+        Self {
+            domain: domain.to_ascii_lowercase(),
+            schemes: vec!["https".to_string()],
+        }
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        match self.domain.strip_prefix("*.") {
+            Some(parent) => host
+                .strip_suffix(parent)
+                .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            None => host == self.domain,
+        }
+    }
+
+    pub fn allows_scheme(&self, scheme: &str) -> bool {
+        self.schemes.iter().any(|allowed| allowed == scheme)
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    // only for testing, as it would let any user replace the administrator's file
+    if cfg!(debug_assertions) {
+        if let Ok(path) = std::env::var("PAIGALDAJA_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        program_data_dir().map(|path| path.join("Paigaldaja").join("config.json"))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Some(PathBuf::from(
+            "/Library/Application Support/Paigaldaja/config.json",
+        ))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Some(PathBuf::from("/etc/paigaldaja/config.json"))
+    }
+}
+
+/// Asks Windows for the ProgramData folder; the `ProgramData` environment
+/// variable can be changed by any user.
+#[cfg(target_os = "windows")]
+fn program_data_dir() -> Option<PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+    use windows_sys::Win32::{
+        System::Com::CoTaskMemFree,
+        UI::Shell::{FOLDERID_ProgramData, SHGetKnownFolderPath, KF_FLAG_DEFAULT},
+    };
+    let mut path = std::ptr::null_mut();
+    // SAFETY: on success `path` is a NUL-terminated string; it has to be freed either way
+    unsafe {
+        let result = SHGetKnownFolderPath(&FOLDERID_ProgramData, KF_FLAG_DEFAULT, 0, &mut path);
+        let dir = (result == 0 && !path.is_null()).then(|| {
+            let len = (0..).take_while(|&i| *path.add(i) != 0).count();
+            PathBuf::from(std::ffi::OsString::from_wide(std::slice::from_raw_parts(
+                path, len,
+            )))
+        });
+        CoTaskMemFree(path as *const _);
+        dir
+    }
+}
+
+/// Loads the config file, falling back to the compiled defaults if it is
+/// missing. If it is invalid, the error is kept in [`Config::load_error`] and
+/// installs are refused.
+pub fn load() -> Config {
+    let Some(path) = config_path() else {
+        return Config::default();
+    };
+    if !path.is_file() {
+        return Config::default();
+    }
+    match std::fs::read_to_string(&path)
+        .context("Failed to read config file")
+        .and_then(|json| serde_json::from_str(&json).context("Failed to parse config file"))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            let error = format!("{e:#} at {}", path.to_string_lossy());
+            eprintln!("{error}");
+            Config {
+                load_error: Some(error),
+                ..Config::default()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_matches_subdomains_only() {
+        let rule = DomainRule::new("*.example.com");
+        assert!(rule.matches("cdn.example.com"));
+        assert!(rule.matches("a.b.example.com"));
+        assert!(rule.matches("CDN.Example.com"));
+        assert!(!rule.matches("example.com"));
+        assert!(!rule.matches("evilexample.com"));
+        assert!(!rule.matches("example.com.evil.net"));
+    }
+
+    #[test]
+    fn bare_domain_matches_exactly() {
+        let rule = DomainRule::new("Example.com");
+        assert!(rule.matches("example.com"));
+        assert!(!rule.matches("cdn.example.com"));
+        assert!(!rule.matches("evilexample.com"));
+    }
+
+    #[test]
+    fn rules_parse_from_strings_and_objects() {
+        let rules: Vec<DomainRule> = serde_json::from_str(
+            r#"["cdn.modrinth.com", { "domain": "Mirror.internal", "schemes": ["HTTP", "https"] }]"#,
+        )
+        .unwrap();
+        assert_eq!(rules[0].schemes, ["https"]);
+        assert_eq!(rules[1].domain, "mirror.internal");
+        assert!(rules[1].allows_scheme("http"));
+        assert!(rules[1].allows_scheme("https"));
+    }
+}
@@ -0,0 +1,371 @@
+use std::{cmp::Ordering, collections::HashMap, fmt};
+
+use crate::{flexver, mods};
+
+/// A mod jar in the profile, with everything it and its nested jars declare.
+pub struct InstalledMod {
+    /// Relative to the profile directory.
+    pub path: String,
+    /// Whether the jar is part of the pack, as opposed to added by the user.
+    pub managed: bool,
+    pub mods: Vec<mods::ModMetadata>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IssueKind {
+    /// A `depends` entry that no mod provides.
+    Missing,
+    /// A `depends` entry that is provided, but not in a matching version.
+    WrongVersion,
+    /// A `breaks` entry that matches an installed mod.
+    Breaks,
+    /// A `conflicts` entry that matches an installed mod.
+    Conflicts,
+}
+
+/// A relation between mods that the loader would refuse or warn about.
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub mod_id: String,
+    pub path: String,
+    pub dependency: String,
+    pub requirement: String,
+    /// Versions of `dependency` that are installed.
+    pub found: Vec<String>,
+    /// Whether the pack can't load because of this. Only wrong versions and
+    /// `breaks` between mods of the pack and the loader are fatal: issues
+    /// caused by mods the user added are only warnings, as those mods can be
+    /// removed, and missing dependencies may be provided in ways that can't be
+    /// seen from the jars.
+    pub fatal: bool,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let found = self.found.join(", ");
+        match self.kind {
+            IssueKind::Missing => write!(
+                f,
+                "{} ({}) requires {} {}, which is not installed",
+                self.mod_id, self.path, self.dependency, self.requirement
+            ),
+            IssueKind::WrongVersion => write!(
+                f,
+                "{} ({}) requires {} {}, but {} is installed",
+                self.mod_id, self.path, self.dependency, self.requirement, found
+            ),
+            IssueKind::Breaks => write!(
+                f,
+                "{} ({}) is incompatible with {} {}",
+                self.mod_id, self.path, self.dependency, found
+            ),
+            IssueKind::Conflicts => write!(
+                f,
+                "{} ({}) may not work with {} {}",
+                self.mod_id, self.path, self.dependency, found
+            ),
+        }
+    }
+}
+
+/// The mod loader a pack uses, with its version.
+#[derive(Clone, Copy, Debug)]
+pub enum Loader<'a> {
+    Fabric(&'a str),
+    Quilt(&'a str),
+}
+
+struct Provider<'a> {
+    /// `None` if the version isn't known, which satisfies every requirement.
+    version: Option<&'a str>,
+    managed: bool,
+}
+
+/// Checks the relations declared by `installed` against each other, the given
+/// Minecraft version and the mods built into the loader, like the loader does
+/// on launch.
+pub fn check(
+    installed: &[InstalledMod],
+    minecraft_version: &str,
+    loader: Option<Loader>,
+) -> Vec<Issue> {
+    let mut providers: HashMap<&str, Vec<Provider>> = HashMap::new();
+    let mut builtin = |id, version| {
+        providers.entry(id).or_default().push(Provider {
+            version,
+            managed: true,
+        })
+    };
+    builtin("minecraft", Some(minecraft_version));
+    // the Java version is only known once the launcher picks a runtime
+    builtin("java", None);
+    match loader {
+        Some(Loader::Fabric(version)) => {
+            builtin("fabricloader", Some(version));
+            // bundled since 0.15, in a version that isn't in the pack
+            if flexver::compare(version, "0.15") != Ordering::Less {
+                builtin("mixinextras", None);
+            }
+        }
+        Some(Loader::Quilt(version)) => {
+            builtin("quilt_loader", Some(version));
+            // Quilt Loader provides fabricloader too, but in a version of its own
+            builtin("fabricloader", None);
+        }
+        None => builtin("fabricloader", None),
+    }
+    for jar in installed {
+        for metadata in &jar.mods {
+            for id in metadata.ids() {
+                providers.entry(id).or_default().push(Provider {
+                    version: Some(&metadata.version),
+                    managed: jar.managed,
+                });
+            }
+        }
+    }
+
+    let mut issues = vec![];
+    for jar in installed {
+        for metadata in &jar.mods {
+            let issue = |kind, dependency: &str, requirement: &[String], found, fatal| Issue {
+                kind,
+                mod_id: metadata.id.clone(),
+                path: jar.path.clone(),
+                dependency: dependency.to_string(),
+                requirement: requirement.join(" || "),
+                found,
+                fatal,
+            };
+            for (dependency, requirement) in &metadata.depends {
+                let candidates = providers
+                    .get(dependency.as_str())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                if candidates.is_empty() {
+                    issues.push(issue(
+                        IssueKind::Missing,
+                        dependency,
+                        requirement,
+                        vec![],
+                        false,
+                    ));
+                } else if !candidates.iter().any(|p| satisfies(p.version, requirement)) {
+                    issues.push(issue(
+                        IssueKind::WrongVersion,
+                        dependency,
+                        requirement,
+                        versions(candidates.iter()),
+                        jar.managed && candidates.iter().all(|p| p.managed),
+                    ));
+                }
+            }
+            for (kind, relations) in [
+                (IssueKind::Breaks, &metadata.breaks),
+                (IssueKind::Conflicts, &metadata.conflicts),
+            ] {
+                for (dependency, requirement) in relations {
+                    let matching: Vec<&Provider> = providers
+                        .get(dependency.as_str())
+                        .into_iter()
+                        .flatten()
+                        .filter(|p| p.version.is_some() && satisfies(p.version, requirement))
+                        .collect();
+                    if !matching.is_empty() {
+                        let fatal = kind == IssueKind::Breaks
+                            && jar.managed
+                            && matching.iter().all(|p| p.managed);
+                        issues.push(issue(
+                            kind,
+                            dependency,
+                            requirement,
+                            versions(matching.into_iter()),
+                            fatal,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
+
+fn versions<'a>(providers: impl Iterator<Item = &'a Provider<'a>>) -> Vec<String> {
+    providers
+        .map(|p| p.version.unwrap_or("*").to_string())
+        .collect()
+}
+
+/// Whether `version` matches any of the alternatives in `requirement`, each of
+/// which may combine further alternatives with `||`.
+fn satisfies(version: Option<&str>, requirement: &[String]) -> bool {
+    let Some(version) = version else {
+        return true;
+    };
+    requirement
+        .iter()
+        .flat_map(|alternatives| alternatives.split("||"))
+        .any(|alternative| {
+            alternative
+                .split_whitespace()
+                .all(|predicate| matches_predicate(version, predicate))
+        })
+}
+
+/// Evaluates one Fabric version predicate, such as `>=1.20`, `~1.20.1` or `1.20.x`.
+fn matches_predicate(version: &str, predicate: &str) -> bool {
+    if predicate == "*" {
+        return true;
+    }
+    let operators = [">=", "<=", ">", "<", "=", "^", "~"];
+    let (operator, required) = operators
+        .iter()
+        .find_map(|op| Some((*op, predicate.strip_prefix(op)?)))
+        .unwrap_or(("=", predicate));
+    let ordering = flexver::compare(version, required);
+    match operator {
+        ">=" => ordering.is_ge(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        "<" => ordering.is_lt(),
+        "^" => ordering.is_ge() && same_components(version, required, 1),
+        "~" => ordering.is_ge() && same_components(version, required, 2),
+        _ => match required.strip_suffix(".x").or(required.strip_suffix(".*")) {
+            Some(prefix) => same_components(version, prefix, prefix.split('.').count()),
+            None => ordering == Ordering::Equal,
+        },
+    }
+}
+
+/// Whether the first `count` dot-separated components of two versions are equal.
+fn same_components(a: &str, b: &str, count: usize) -> bool {
+    let components = |version: &str| -> Vec<String> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .take(count)
+            .map(str::to_string)
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    a.len() == b.len()
+        && a.iter()
+            .zip(&b)
+            .all(|(a, b)| flexver::compare(a, b) == Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfies_str(version: &str, requirement: &str) -> bool {
+        satisfies(Some(version), &[requirement.to_string()])
+    }
+
+    #[test]
+    fn comparison_predicates() {
+        assert!(satisfies_str("1.20.1", ">=1.20"));
+        assert!(!satisfies_str("1.19.4", ">=1.20"));
+        assert!(satisfies_str("1.20.1", "<1.20.2"));
+        assert!(satisfies_str("1.20.1", "1.20.1"));
+        assert!(satisfies_str("1.20.1", "=1.20.1"));
+        assert!(!satisfies_str("1.20.2", "1.20.1"));
+        assert!(satisfies_str("1.20.1", "*"));
+        assert!(satisfies_str("1.20.1", ">=1.20 <1.21"));
+        assert!(!satisfies_str("1.21", ">=1.20 <1.21"));
+    }
+
+    #[test]
+    fn tilde_and_caret() {
+        assert!(satisfies_str("1.20.4", "~1.20.1"));
+        assert!(!satisfies_str("1.21", "~1.20.1"));
+        assert!(!satisfies_str("1.20", "~1.20.1"));
+        assert!(satisfies_str("1.21.3", "^1.20.1"));
+        assert!(!satisfies_str("2.0.0", "^1.20.1"));
+        assert!(!satisfies_str("1.19", "^1.20.1"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(satisfies_str("1.20.4", "1.20.x"));
+        assert!(satisfies_str("1.20", "1.20.x"));
+        assert!(!satisfies_str("1.21.1", "1.20.x"));
+        assert!(satisfies_str("1.20.4", "1.20.*"));
+    }
+
+    #[test]
+    fn alternatives() {
+        assert!(satisfies_str("1.19.2", "1.19.2 || >=1.20"));
+        assert!(satisfies_str("1.20.4", "1.19.2 || >=1.20"));
+        assert!(!satisfies_str("1.19.4", "1.19.2 || >=1.20"));
+        assert!(satisfies(
+            Some("1.19.2"),
+            &["1.18.2".to_string(), "1.19.2".to_string()]
+        ));
+        assert!(satisfies(None, &["1.19.2".to_string()]));
+    }
+
+    #[test]
+    fn trailing_dash() {
+        assert!(satisfies_str("1.20.1", ">=1.20.1-"));
+        assert!(satisfies_str("1.20.1-rc.1", ">=1.20.1-"));
+        assert!(!satisfies_str("1.20", ">=1.20.1-"));
+        assert!(satisfies_str("0.15.3", ">=0.15.0-"));
+        assert!(satisfies_str("1.20.4", "~1.20.1-"));
+    }
+
+    fn jar(
+        path: &str,
+        managed: bool,
+        id: &str,
+        version: &str,
+        depends: &[(&str, &str)],
+    ) -> InstalledMod {
+        InstalledMod {
+            path: path.to_string(),
+            managed,
+            mods: vec![mods::ModMetadata {
+                id: id.to_string(),
+                name: None,
+                version: version.to_string(),
+                provides: vec![],
+                depends: depends
+                    .iter()
+                    .map(|(id, requirement)| (id.to_string(), vec![requirement.to_string()]))
+                    .collect(),
+                breaks: Default::default(),
+                conflicts: Default::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn wrong_loader_version_is_fatal() {
+        let installed = [jar(
+            "mods/a.jar",
+            true,
+            "a",
+            "1.0",
+            &[("fabricloader", ">=0.16"), ("minecraft", "1.20.x")],
+        )];
+        let issues = check(&installed, "1.20.1", Some(Loader::Fabric("0.15.11")));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::WrongVersion);
+        assert!(issues[0].fatal);
+    }
+
+    #[test]
+    fn builtin_mods_and_missing_dependencies() {
+        let installed = [
+            jar("mods/a.jar", true, "a", "1.0", &[("mixinextras", ">=0.2")]),
+            jar("mods/b.jar", true, "b", "1.0", &[("c", "*")]),
+        ];
+        let issues = check(&installed, "1.20.1", Some(Loader::Fabric("0.15.11")));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::Missing);
+        assert!(!issues[0].fatal);
+    }
+}
@@ -0,0 +1,134 @@
+//! Port of FlexVer (https://github.com/unascribed/FlexVer), the version
+//! ordering also used by `src/lib/flexver.ts`.
+
+use std::cmp::Ordering;
+
+enum Component {
+    Numeric(Vec<char>),
+    Text(Vec<char>),
+    Prerelease(Vec<char>),
+}
+
+impl Component {
+    fn new(number: bool, chars: Vec<char>) -> Self {
+        if number {
+            Component::Numeric(chars)
+        } else if chars.len() > 1 && chars[0] == '-' {
+            Component::Prerelease(chars)
+        } else {
+            Component::Text(chars)
+        }
+    }
+
+    fn chars(&self) -> &[char] {
+        match self {
+            Component::Numeric(chars) | Component::Text(chars) | Component::Prerelease(chars) => {
+                chars
+            }
+        }
+    }
+}
+
+fn decompose(version: &str) -> Vec<Component> {
+    let mut out = vec![];
+    let Some(first) = version.chars().next() else {
+        return out;
+    };
+    let mut last_was_number = first.is_ascii_digit();
+    let mut accum: Vec<char> = vec![];
+    for c in version.chars() {
+        // appendices are ignored
+        if c == '+' {
+            break;
+        }
+        let number = c.is_ascii_digit();
+        if number != last_was_number || (c == '-' && !accum.is_empty() && accum[0] != '-') {
+            out.push(Component::new(last_was_number, std::mem::take(&mut accum)));
+            last_was_number = number;
+        }
+        accum.push(c);
+    }
+    // a trailing `-`, as in Fabric's `>=1.20.1-`, sorts before every pre-release
+    if accum == ['-'] {
+        out.push(Component::Prerelease(accum));
+    } else {
+        out.push(Component::new(last_was_number, accum));
+    }
+    out
+}
+
+fn compare_components(a: Option<&Component>, b: Option<&Component>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => compare_components(b, a).reverse(),
+        (Some(Component::Prerelease(_)), None) => Ordering::Less,
+        (Some(_), None) => Ordering::Greater,
+        (Some(Component::Numeric(a)), Some(Component::Numeric(b))) => {
+            let a = strip_leading_zeroes(a);
+            let b = strip_leading_zeroes(b);
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        }
+        (Some(a), Some(b)) => a.chars().cmp(b.chars()),
+    }
+}
+
+fn strip_leading_zeroes(digits: &[char]) -> &[char] {
+    let zeroes = digits[..digits.len() - 1]
+        .iter()
+        .take_while(|&&c| c == '0')
+        .count();
+    &digits[zeroes..]
+}
+
+/// Compares two version strings, treating runs of digits as numbers and
+/// `-suffixes` as pre-releases. A bare trailing `-` is the lowest pre-release.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let a = decompose(a);
+    let b = decompose(b);
+    (0..a.len().max(b.len()))
+        .map(|i| compare_components(a.get(i), b.get(i)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// From FlexVer's `test/test_vectors.txt`.
+    const TEST_VECTORS: &[(&str, Ordering, &str)] = &[
+        ("b1.7.3", Ordering::Greater, "a1.2.6"),
+        ("b1.2.6", Ordering::Greater, "a1.7.3"),
+        ("a1.1.2", Ordering::Less, "a1.1.2_01"),
+        ("1.16.5-0.00.5", Ordering::Greater, "1.14.2-1.3.7"),
+        ("1.0.0", Ordering::Less, "1.0.0_01"),
+        ("1.0.1", Ordering::Greater, "1.0.0_01"),
+        ("1.0.0_01", Ordering::Less, "1.0.1"),
+        ("0.17.1-beta.1", Ordering::Less, "0.17.1"),
+        ("0.17.1-beta.1", Ordering::Less, "0.17.1-beta.2"),
+        ("1.4.5_01", Ordering::Equal, "1.4.5_01+fabric-1.17"),
+        ("1.4.5_01", Ordering::Equal, "1.4.5_01+fabric-1.17+ohgod"),
+        ("14w16a", Ordering::Less, "18w40b"),
+        ("18w40a", Ordering::Less, "18w40b"),
+        ("1.4.5_01+fabric-1.17", Ordering::Less, "18w40b"),
+        ("13w02a", Ordering::Less, "c0.3.0_01"),
+        ("0.6.0-1.18.x", Ordering::Less, "0.9.beta-1.18.x"),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for &(a, expected, b) in TEST_VECTORS {
+            assert_eq!(compare(a, b), expected, "{a} vs {b}");
+            assert_eq!(compare(b, a), expected.reverse(), "{b} vs {a}");
+        }
+    }
+
+    #[test]
+    fn trailing_dash_is_lowest_prerelease() {
+        assert_eq!(compare("1.20.1", "1.20.1-"), Ordering::Greater);
+        assert_eq!(compare("1.20.1-beta.1", "1.20.1-"), Ordering::Greater);
+        assert_eq!(compare("1.20.1-", "1.20.1-"), Ordering::Equal);
+        assert_eq!(compare("1.20", "1.20.1-"), Ordering::Less);
+        assert_eq!(compare("1.20.2", "1.20.1-"), Ordering::Greater);
+    }
+}
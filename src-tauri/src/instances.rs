@@ -0,0 +1,132 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+
+use crate::{launcher, meta};
+
+/// Directory in the launcher folder that instances get their game directory in,
+/// unless one is chosen explicitly.
+pub const INSTANCES_DIR: &str = "instances";
+
+/// A named installation of a pack, next to other installations of the same pack.
+pub struct Instance {
+    /// Key of the launcher profile.
+    pub profile_id: String,
+    pub profile_name: String,
+    pub profile_dir: Option<String>,
+}
+
+/// Turns an instance name into something usable in profile keys and paths,
+/// e.g. `Beta 2` into `beta-2`.
+pub fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Derives the profile key, profile name and game directory of the instance
+/// `name` of a pack. An explicitly chosen `profile_dir` is kept.
+pub fn resolve(
+    pack_id: &str,
+    pack_name: &str,
+    name: &str,
+    profile_dir: Option<String>,
+) -> anyhow::Result<Instance> {
+    let slug = slug(name);
+    if slug.is_empty() {
+        return Err(anyhow!(
+            "Instance name {:?} must contain letters or digits",
+            name
+        ));
+    }
+    let profile_id = format!("{pack_id}-{slug}");
+    Ok(Instance {
+        profile_dir: Some(profile_dir.unwrap_or_else(|| {
+            Path::new(INSTANCES_DIR)
+                .join(&profile_id)
+                .to_string_lossy()
+                .to_string()
+        })),
+        profile_name: format!("{pack_name} ({name})"),
+        profile_id,
+    })
+}
+
+/// An installation the installer manages, as recorded in its metadata.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceInfo {
+    /// `None` for the pack's default installation.
+    pub instance: Option<String>,
+    pub pack_id: Option<String>,
+    pub pack_name: Option<String>,
+    pub profile_id: Option<String>,
+    pub version_id: Option<String>,
+    pub minecraft_version: Option<String>,
+    pub game_dir: PathBuf,
+}
+
+impl InstanceInfo {
+    pub fn new(game_dir: PathBuf, meta: meta::InstallMetadata) -> Self {
+        Self {
+            instance: meta.instance,
+            pack_id: meta.pack_id,
+            pack_name: meta.pack_name,
+            profile_id: meta.profile.map(|profile| profile.id),
+            version_id: meta.version_id,
+            minecraft_version: meta.minecraft_version,
+            game_dir,
+        }
+    }
+}
+
+/// Finds every installation the installer manages: in the launcher directory
+/// itself, in its instances directory and in the game directory of every
+/// launcher profile. Returns each game directory with its metadata.
+pub async fn list(launcher_dir: &Path) -> anyhow::Result<Vec<(PathBuf, meta::InstallMetadata)>> {
+    let mut game_dirs = vec![launcher_dir.to_owned()];
+    match tokio::fs::read_dir(launcher_dir.join(INSTANCES_DIR)).await {
+        Ok(mut entries) => {
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .context("Failed to list instances")?
+            {
+                game_dirs.push(entry.path());
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        Err(e) => return Err(e).context("Failed to list instances"),
+    }
+    // profiles may point anywhere; unreadable profiles just mean there's nothing more to find
+    if let Ok(profiles) = launcher::read(&launcher_dir.join("launcher_profiles.json")).await {
+        game_dirs.extend(
+            profiles
+                .profiles
+                .into_values()
+                .filter_map(|profile| profile.game_dir.flatten())
+                .map(|game_dir| launcher_dir.join(game_dir)),
+        );
+    }
+    let mut seen = HashSet::new();
+    let mut instances = vec![];
+    for game_dir in game_dirs {
+        let canonical = tokio::fs::canonicalize(&game_dir)
+            .await
+            .unwrap_or_else(|_| game_dir.clone());
+        if !seen.insert(canonical) {
+            continue;
+        }
+        // a broken metadata file shouldn't hide the other instances
+        if let Ok(Some(meta)) = meta::read(&game_dir).await {
+            instances.push((game_dir, meta));
+        }
+    }
+    Ok(instances)
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use sha2::Digest;
+use tokio::io::AsyncReadExt;
+
+use crate::{meta, mods, mrpack};
+
+/// Differences between an installed profile and the pack index it was installed from.
+/// All paths are relative to the profile directory.
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Files from the index that are no longer present.
+    pub missing: Vec<String>,
+    /// Files from the index whose SHA-512 no longer matches.
+    pub modified: Vec<String>,
+    /// Jars in `mods/` that were neither downloaded nor extracted by the install.
+    pub extra: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_intact(&self) -> bool {
+        self.missing.is_empty() && self.modified.is_empty()
+    }
+}
+
+pub async fn sha512_file(path: &Path) -> std::io::Result<[u8; 64]> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = sha2::Sha512::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(hasher.finalize().into());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+/// Checks the downloads in `index` and lists the jars that aren't among the
+/// `files` the install recorded.
+pub async fn verify(
+    profile_dir: &Path,
+    index: &mrpack::PackIndex,
+    files: &[meta::InstalledFile],
+) -> anyhow::Result<IntegrityReport> {
+    let mut report = IntegrityReport::default();
+    for file in &index.files {
+        if file.is_client_unsupported() {
+            continue;
+        }
+        let path = crate::parse_and_sanitize_path(&file.path)
+            .ok_or(anyhow!("Possibly malicious download path: {}", file.path))?;
+        let path = profile_dir.join(path);
+        let expected = hex::decode(
+            file.hashes
+                .get(&mrpack::PackFileHash::Sha512)
+                .ok_or(anyhow!("No SHA512 hash for file {}", file.path))?,
+        )?;
+        match sha512_file(&path).await {
+            Ok(hash) if hash[..] == expected[..] => (),
+            Ok(_) => report.modified.push(file.path.clone()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                report.missing.push(file.path.clone())
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", file.path));
+            }
+        }
+    }
+    let managed: HashSet<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+    report.extra = mods::list_jars(profile_dir)
+        .await?
+        .into_iter()
+        .filter(|jar| !managed.contains(jar.as_path()))
+        .map(|jar| jar.to_string_lossy().replace('\\', "/"))
+        .collect();
+    Ok(report)
+}
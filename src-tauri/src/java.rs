@@ -0,0 +1,577 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha1::Digest;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{config, net};
+
+const VERSION_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+/// Lists the Java runtimes the official launcher downloads, by platform and component.
+const RUNTIME_MANIFEST_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+/// Used by versions whose JSON predates `javaVersion`.
+const LEGACY_RUNTIME_COMPONENT: &str = "jre-legacy";
+
+#[cfg(target_os = "windows")]
+const JAVA_EXECUTABLE: &str = "java.exe";
+#[cfg(not(target_os = "windows"))]
+const JAVA_EXECUTABLE: &str = "java";
+
+/// A Java installation found on this system.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaRuntime {
+    /// The `java` executable; on Windows, `javaw.exe` if there is one.
+    pub path: PathBuf,
+    pub version: String,
+    pub major_version: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaReport {
+    /// `javaVersion.majorVersion` of the Minecraft version, if it has one.
+    pub required_major_version: Option<u32>,
+    pub runtimes: Vec<JavaRuntime>,
+    /// The runtime [`find_runtimes`] would pick for the profile.
+    pub selected: Option<JavaRuntime>,
+}
+
+/// Directories that may contain a Java installation, most specific first.
+fn candidate_homes() -> Vec<PathBuf> {
+    let mut homes = vec![];
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(java_home));
+    }
+    if let Some(path) = std::env::var_os("PATH") {
+        // PATH contains `bin` directories
+        homes.extend(
+            std::env::split_paths(&path)
+                .filter(|dir| dir.join(JAVA_EXECUTABLE).is_file())
+                .filter_map(|dir| dir.parent().map(Path::to_owned)),
+        );
+    }
+    #[cfg(target_os = "windows")]
+    let roots: Vec<PathBuf> = ["ProgramFiles", "ProgramFiles(x86)"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .flat_map(|program_files| {
+            [
+                "Java",
+                "Eclipse Adoptium",
+                "Microsoft",
+                "Zulu",
+                "BellSoft",
+                "Amazon Corretto",
+            ]
+            .iter()
+            .map(move |vendor| PathBuf::from(&program_files).join(vendor))
+        })
+        .collect();
+    #[cfg(target_os = "macos")]
+    let roots = vec![PathBuf::from("/Library/Java/JavaVirtualMachines")];
+    #[cfg(target_os = "linux")]
+    let roots = vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/usr/lib64/jvm"),
+        PathBuf::from("/usr/java"),
+        PathBuf::from("/opt/java"),
+    ];
+    for root in roots {
+        let Ok(entries) = std::fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            #[cfg(target_os = "macos")]
+            homes.push(entry.path().join("Contents/Home"));
+            #[cfg(not(target_os = "macos"))]
+            homes.push(entry.path());
+        }
+    }
+    if let Some(Ok(entries)) = runtimes_dir().ok().map(std::fs::read_dir) {
+        homes.extend(entries.flatten().map(|entry| runtime_home(&entry.path())));
+    }
+    homes
+}
+
+/// Lists the Java installations on this system, without duplicates.
+pub fn detect_runtimes() -> Vec<JavaRuntime> {
+    let mut seen = HashSet::new();
+    let mut runtimes = vec![];
+    for home in candidate_homes() {
+        let executable = home.join("bin").join(JAVA_EXECUTABLE);
+        let Ok(canonical) = std::fs::canonicalize(&executable) else {
+            continue;
+        };
+        if !seen.insert(canonical) {
+            continue;
+        }
+        if let Some(runtime) = inspect(&home, &executable) {
+            runtimes.push(runtime);
+        }
+    }
+    runtimes
+}
+
+fn inspect(home: &Path, executable: &Path) -> Option<JavaRuntime> {
+    // the release file is cheaper than starting a JVM, but not every distribution has one
+    let version = std::fs::read_to_string(home.join("release"))
+        .ok()
+        .and_then(|release| {
+            release.lines().find_map(|line| {
+                Some(
+                    line.strip_prefix("JAVA_VERSION=")?
+                        .trim_matches('"')
+                        .to_string(),
+                )
+            })
+        })
+        .or_else(|| version_from_executable(executable))?;
+    #[cfg(target_os = "windows")]
+    let path = Some(executable.with_file_name("javaw.exe"))
+        .filter(|javaw| javaw.is_file())
+        .unwrap_or_else(|| executable.to_owned());
+    #[cfg(not(target_os = "windows"))]
+    let path = executable.to_owned();
+    Some(JavaRuntime {
+        path,
+        major_version: parse_major_version(&version)?,
+        version,
+    })
+}
+
+fn version_from_executable(executable: &Path) -> Option<String> {
+    let output = crate::background_command(executable)
+        .arg("-version")
+        .output()
+        .ok()?;
+    // `java -version` prints e.g. `openjdk version "17.0.8" 2023-07-18` to stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let start = stderr.find('"')? + 1;
+    let end = start + stderr[start..].find('"')?;
+    Some(stderr[start..end].to_string())
+}
+
+/// Turns `1.8.0_382` into 8 and `17.0.8` or `21` into 17 and 21.
+pub fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Picks the runtime to use for a Minecraft version that needs `required`:
+/// the same major version if there is one, otherwise the oldest newer one.
+pub fn select_runtime(runtimes: &[JavaRuntime], required: Option<u32>) -> Option<&JavaRuntime> {
+    let required = required.unwrap_or(8);
+    runtimes
+        .iter()
+        .filter(|runtime| runtime.major_version >= required)
+        .min_by_key(|runtime| runtime.major_version)
+}
+
+#[derive(Deserialize)]
+struct VersionManifest {
+    versions: Vec<VersionManifestEntry>,
+}
+
+#[derive(Deserialize)]
+struct VersionManifestEntry {
+    id: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionJson {
+    java_version: Option<JavaVersion>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JavaVersion {
+    component: Option<String>,
+    major_version: u32,
+}
+
+/// Looks up `javaVersion` of a Minecraft version, preferring the version JSON
+/// the launcher already downloaded. Old versions don't have one.
+async fn java_version(
+    client: &reqwest::Client,
+    config: &config::Config,
+    minecraft_version: &str,
+) -> anyhow::Result<Option<JavaVersion>> {
+    let local_path = crate::get_launcher_path()
+        .await?
+        .join("versions")
+        .join(minecraft_version)
+        .join(format!("{minecraft_version}.json"));
+    if let Ok(json) = tokio::fs::read_to_string(&local_path).await {
+        if let Ok(version) = serde_json::from_str::<VersionJson>(&json) {
+            return Ok(version.java_version);
+        }
+    }
+    let manifest: VersionManifest = net::with_retry(&config.retry, || {
+        fetch_json(client, config, VERSION_MANIFEST_URL)
+    })
+    .await
+    .context("Failed to fetch Minecraft version manifest")?;
+    let entry = manifest
+        .versions
+        .into_iter()
+        .find(|entry| entry.id == minecraft_version)
+        .ok_or(anyhow!("Unknown Minecraft version {}", minecraft_version))?;
+    let version: VersionJson =
+        net::with_retry(&config.retry, || fetch_json(client, config, &entry.url))
+            .await
+            .context("Failed to fetch Minecraft version metadata")?;
+    Ok(version.java_version)
+}
+
+pub async fn required_major_version(
+    client: &reqwest::Client,
+    config: &config::Config,
+    minecraft_version: &str,
+) -> anyhow::Result<Option<u32>> {
+    Ok(java_version(client, config, minecraft_version)
+        .await?
+        .map(|version| version.major_version))
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    config: &config::Config,
+    url: &str,
+) -> anyhow::Result<T> {
+    let url = crate::revalidate_url(url, &config.mojang_domains)?;
+    let response = client.get(url).send().await?;
+    if response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &response,
+            "Metadata server did not respond with 200".to_string(),
+        ));
+    }
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+/// Finds the installed runtimes and checks them against what `minecraft_version` needs.
+pub async fn find_runtimes(
+    client: &reqwest::Client,
+    config: &config::Config,
+    minecraft_version: Option<&str>,
+) -> anyhow::Result<JavaReport> {
+    let required_major_version = match minecraft_version {
+        Some(version) => required_major_version(client, config, version).await?,
+        None => None,
+    };
+    // scans directories and starts `java -version`
+    let runtimes = tauri::async_runtime::spawn_blocking(detect_runtimes)
+        .await
+        .context("Failed to detect Java runtimes")?;
+    let selected = select_runtime(&runtimes, required_major_version).cloned();
+    Ok(JavaReport {
+        required_major_version,
+        runtimes,
+        selected,
+    })
+}
+
+#[derive(Deserialize)]
+struct RuntimeEntry {
+    manifest: RuntimeDownload,
+}
+
+#[derive(Deserialize)]
+struct RuntimeDownload {
+    sha1: String,
+    size: u64,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RuntimeManifest {
+    files: BTreeMap<String, RuntimeFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Directory,
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct RuntimeFileDownloads {
+    raw: RuntimeDownload,
+}
+
+/// Name of this platform in Mojang's runtime manifest.
+fn runtime_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        _ => None,
+    }
+}
+
+/// Where downloaded runtimes are kept, one directory per component.
+pub fn runtimes_dir() -> anyhow::Result<PathBuf> {
+    Ok(tauri::api::path::local_data_dir()
+        .ok_or(anyhow!("Could not determine local data directory!"))?
+        .join("Paigaldaja")
+        .join("runtime"))
+}
+
+/// The Java home inside a runtime component's directory.
+fn runtime_home(component_dir: &Path) -> PathBuf {
+    #[cfg(target_os = "macos")]
+    return component_dir.join("jre.bundle/Contents/Home");
+    #[cfg(not(target_os = "macos"))]
+    return component_dir.to_owned();
+}
+
+/// Downloads the Java runtime Mojang ships for `minecraft_version` into
+/// [`runtimes_dir`], checking every file against the SHA-1 in the runtime
+/// manifest. Files that are already present and intact are kept. Returns the
+/// `java` executable.
+pub async fn install_runtime(
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    minecraft_version: &str,
+) -> anyhow::Result<PathBuf> {
+    let component = java_version(client, config, minecraft_version)
+        .await?
+        .and_then(|version| version.component)
+        .unwrap_or_else(|| LEGACY_RUNTIME_COMPONENT.to_string());
+    let platform =
+        runtime_platform().ok_or(anyhow!("Mojang provides no Java runtime for this platform"))?;
+    let mut runtimes: HashMap<String, HashMap<String, Vec<RuntimeEntry>>> =
+        net::with_retry(&config.retry, || {
+            fetch_json(client, config, RUNTIME_MANIFEST_URL)
+        })
+        .await
+        .context("Failed to fetch Java runtime list")?;
+    let entry = runtimes
+        .get_mut(platform)
+        .and_then(|components| components.remove(&component))
+        .and_then(|entries| entries.into_iter().next())
+        .ok_or(anyhow!(
+            "Mojang provides no {} runtime for {}",
+            component,
+            platform
+        ))?;
+    let manifest = net::with_retry(&config.retry, || {
+        fetch_verified(client, config, limiter, &entry.manifest)
+    })
+    .await
+    .context("Failed to fetch Java runtime manifest")?;
+    let manifest: RuntimeManifest =
+        serde_json::from_slice(&manifest).context("Java runtime manifest is invalid")?;
+
+    let component_dir = runtimes_dir()?.join(&component);
+    for (path, file) in &manifest.files {
+        let relative = crate::parse_and_sanitize_path(path)
+            .ok_or(anyhow!("Possibly malicious runtime path: {}", path))?;
+        let dest = component_dir.join(relative);
+        match file {
+            RuntimeFile::Directory => tokio::fs::create_dir_all(&dest)
+                .await
+                .with_context(|| format!("Failed to create {}", dest.display()))?,
+            RuntimeFile::File {
+                executable,
+                downloads,
+            } => {
+                if let Some(parent) = dest.parent() {
+                    tokio::fs::create_dir_all(parent)
+                        .await
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                let intact = sha1_file(&dest)
+                    .await
+                    .is_ok_and(|hash| hex::encode(hash).eq_ignore_ascii_case(&downloads.raw.sha1));
+                if !intact {
+                    net::with_retry(&config.retry, || {
+                        download_runtime_file(client, config, limiter, &downloads.raw, &dest)
+                    })
+                    .await
+                    .with_context(|| format!("Failed to download {path}"))?;
+                }
+                #[cfg(unix)]
+                if *executable {
+                    use std::os::unix::fs::PermissionsExt;
+                    tokio::fs::set_permissions(&dest, std::fs::Permissions::from_mode(0o755))
+                        .await
+                        .with_context(|| format!("Failed to make {path} executable"))?;
+                }
+                #[cfg(not(unix))]
+                let _ = executable;
+            }
+            RuntimeFile::Link { target } => {
+                if !link_stays_inside(relative, target) {
+                    return Err(anyhow!(
+                        "Possibly malicious runtime link: {} -> {}",
+                        path,
+                        target
+                    ));
+                }
+                // Windows runtimes don't contain links
+                #[cfg(unix)]
+                {
+                    let _ = tokio::fs::remove_file(&dest).await;
+                    tokio::fs::symlink(target, &dest)
+                        .await
+                        .with_context(|| format!("Failed to link {path}"))?;
+                }
+                #[cfg(not(unix))]
+                let _ = target;
+            }
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    let executable = runtime_home(&component_dir).join("bin").join("javaw.exe");
+    #[cfg(not(target_os = "windows"))]
+    let executable = runtime_home(&component_dir)
+        .join("bin")
+        .join(JAVA_EXECUTABLE);
+    if !executable.is_file() {
+        return Err(anyhow!(
+            "Java runtime {} doesn't contain {}",
+            component,
+            executable.display()
+        ));
+    }
+    Ok(executable)
+}
+
+/// Whether a link at `relative` in a component directory, pointing to
+/// `target`, resolves to a path inside the component directory.
+fn link_stays_inside(relative: &Path, target: &str) -> bool {
+    let mut depth = relative
+        .parent()
+        .map_or(0, |parent| parent.components().count());
+    for component in Path::new(target).components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => depth += 1,
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+async fn sha1_file(path: &Path) -> std::io::Result<[u8; 20]> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = sha1::Sha1::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(hasher.finalize().into());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+/// Fetches a small file into memory and checks its size and SHA-1.
+async fn fetch_verified(
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    download: &RuntimeDownload,
+) -> anyhow::Result<Vec<u8>> {
+    let url = crate::revalidate_url(&download.url, &config.mojang_domains)?;
+    let response = client.get(url).send().await?;
+    if response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &response,
+            "Server did not respond with 200".to_string(),
+        ));
+    }
+    let bytes = response.bytes().await?;
+    limiter.throttle(bytes.len()).await;
+    check_download(
+        download,
+        bytes.len() as u64,
+        sha1::Sha1::digest(&bytes).into(),
+    )?;
+    Ok(bytes.to_vec())
+}
+
+/// Streams one runtime file next to `dest` and moves it into place once its
+/// size and SHA-1 match the manifest.
+async fn download_runtime_file(
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    download: &RuntimeDownload,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let url = crate::revalidate_url(&download.url, &config.mojang_domains)?;
+    let mut response = client.get(url).send().await?;
+    if response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &response,
+            "Server did not respond with 200".to_string(),
+        ));
+    }
+    let mut part_name = dest.file_name().unwrap_or_default().to_owned();
+    part_name.push(".part");
+    let part_path = dest.with_file_name(part_name);
+    let mut file = tokio::fs::File::create(&part_path).await?;
+    let mut hasher = sha1::Sha1::new();
+    let mut size = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        size += chunk.len() as u64;
+        limiter.throttle(chunk.len()).await;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    drop(file);
+    if let Err(e) = check_download(download, size, hasher.finalize().into()) {
+        let _ = tokio::fs::remove_file(&part_path).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&part_path, dest).await?;
+    Ok(())
+}
+
+fn check_download(download: &RuntimeDownload, size: u64, hash: [u8; 20]) -> anyhow::Result<()> {
+    if size != download.size {
+        return Err(anyhow!(
+            "Wrong size: got {} bytes, expected {} bytes",
+            size,
+            download.size
+        ));
+    }
+    if !hex::encode(hash).eq_ignore_ascii_case(&download.sha1) {
+        return Err(anyhow!(
+            "Wrong hash: got {}, expected {}",
+            hex::encode(hash),
+            download.sha1
+        ));
+    }
+    Ok(())
+}
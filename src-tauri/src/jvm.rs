@@ -0,0 +1,115 @@
+use serde::Serialize;
+
+/// Memory used when neither the pack nor the system says otherwise.
+const DEFAULT_MINIMUM_MB: u64 = 2048;
+const DEFAULT_RECOMMENDED_MB: u64 = 4096;
+
+/// The launcher's own default arguments, minus `-Xmx`.
+const DEFAULT_GC_ARGS: &str = "-XX:+UnlockExperimentalVMOptions -XX:+UseG1GC -XX:G1NewSizePercent=20 -XX:G1ReservePercent=20 -XX:MaxGCPauseMillis=50 -XX:G1HeapRegionSize=32M";
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryRecommendation {
+    /// `None` if it couldn't be determined.
+    pub total_mb: Option<u64>,
+    pub minimum_mb: u64,
+    pub recommended_mb: u64,
+    /// Whether the pack's minimum leaves less than half of the system's memory
+    /// to everything else. The recommendation never exceeds the total.
+    pub low_memory: bool,
+}
+
+/// Picks a heap size between what the pack needs and what it would like,
+/// leaving at least half of the system's memory to everything else if the
+/// pack's minimum allows it. The pack can set `memory.minimum_mb` and
+/// `memory.recommended_mb` in the extra metadata.
+pub async fn recommend_memory(extra_metadata: &serde_json::Value) -> MemoryRecommendation {
+    let hint = |name: &str| {
+        extra_metadata
+            .get("memory")
+            .and_then(|memory| memory.get(name))
+            .and_then(serde_json::Value::as_u64)
+    };
+    let minimum_mb = hint("minimum_mb").unwrap_or(DEFAULT_MINIMUM_MB);
+    let wanted_mb = hint("recommended_mb")
+        .unwrap_or(DEFAULT_RECOMMENDED_MB)
+        .max(minimum_mb);
+    // may start a process on Windows
+    let total_mb = tauri::async_runtime::spawn_blocking(total_memory_mb)
+        .await
+        .ok()
+        .flatten();
+    let (recommended_mb, low_memory) = match total_mb {
+        Some(total_mb) if minimum_mb > total_mb / 2 => (minimum_mb.min(total_mb), true),
+        Some(total_mb) => (wanted_mb.min(total_mb / 2), false),
+        None => (wanted_mb, false),
+    };
+    MemoryRecommendation {
+        total_mb,
+        minimum_mb,
+        recommended_mb,
+        low_memory,
+    }
+}
+
+/// Builds the profile's JVM arguments: the heap size followed by the pack's
+/// `jvm_args` from the extra metadata, or the launcher's default GC flags.
+pub fn java_args(max_memory_mb: u64, extra_metadata: &serde_json::Value) -> String {
+    let pack_args = extra_metadata
+        .get("jvm_args")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or(DEFAULT_GC_ARGS);
+    std::iter::once(format!("-Xmx{max_memory_mb}M"))
+        .chain(
+            pack_args
+                .split_whitespace()
+                .filter(|arg| !arg.starts_with("-Xmx"))
+                .map(str::to_string),
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(target_os = "linux")]
+fn total_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kb: u64 = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb / 1024)
+}
+
+#[cfg(target_os = "macos")]
+fn total_memory_mb() -> Option<u64> {
+    let output = std::process::Command::new("sysctl")
+        .args(["-n", "hw.memsize"])
+        .output()
+        .ok()?;
+    let bytes: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(bytes / 1024 / 1024)
+}
+
+#[cfg(target_os = "windows")]
+fn total_memory_mb() -> Option<u64> {
+    let output = crate::background_command("powershell")
+        .args([
+            "-NoProfile",
+            "-Command",
+            "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+        ])
+        .output()
+        .ok()?;
+    let bytes: u64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(bytes / 1024 / 1024)
+}
@@ -0,0 +1,393 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Contents of the launcher's `launcher_profiles.json`. Fields the installer
+/// doesn't know about are kept in `extra` and written back unchanged, and so
+/// are explicit `null`s: a missing field is `None`, `null` is `Some(None)`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherProfiles {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub settings: Nullable<Settings>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub version: Nullable<u32>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A field that may be missing, `null` or set.
+pub type Nullable<T> = Option<Option<T>>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub name: Nullable<String>,
+    /// `custom`, `latest-release` or `latest-snapshot`.
+    #[serde(
+        rename = "type",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "nullable"
+    )]
+    pub profile_type: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub created: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub last_used: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub last_version_id: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub game_dir: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub java_dir: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub java_args: Nullable<String>,
+    /// Either the name of a built-in icon or a `data:` URL.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub icon: Nullable<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub resolution: Nullable<ProfileResolution>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A profile's `resolution`. Values the installer can't read, like negative
+/// sizes, are kept as they are instead of failing the whole file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ProfileResolution {
+    Known(Resolution),
+    Other(serde_json::Value),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub enable_snapshots: Nullable<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub keep_launcher_open: Nullable<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "nullable")]
+    pub show_game_log: Nullable<bool>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// (De)serializes [`Nullable`] fields; missing fields are handled by
+/// `default` and `skip_serializing_if`.
+mod nullable {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<super::Nullable<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+
+    pub fn serialize<T, S>(value: &super::Nullable<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        value
+            .as_ref()
+            .and_then(Option::as_ref)
+            .serialize(serializer)
+    }
+}
+
+impl LauncherProfiles {
+    /// Points the profile with the given id at a new version, or adds it if
+    /// there is none. Settings the user changed on an existing profile, like
+    /// its Java runtime or resolution, are kept; its JVM arguments are only
+    /// replaced with `java_args` if `overwrite_java_args` is set.
+    pub fn set_or_create_profile(
+        &mut self,
+        profile_id: &str,
+        profile_name: &str,
+        profile_icon: Option<&str>,
+        profile_version: &str,
+        profile_dir: Option<&str>,
+        java_args: Option<&str>,
+        overwrite_java_args: bool,
+    ) -> anyhow::Result<()> {
+        let profile = match self.profiles.entry(profile_id.to_string()) {
+            std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                let now = time::OffsetDateTime::now_utc()
+                    .format(&time::format_description::well_known::Iso8601::DEFAULT)
+                    .context("Failed to format the current time")?;
+                entry.insert(Profile {
+                    profile_type: Some(Some("custom".to_string())),
+                    created: Some(Some(now.clone())),
+                    last_used: Some(Some(now)),
+                    java_args: java_args.map(|args| Some(args.to_string())),
+                    ..Default::default()
+                })
+            }
+        };
+        profile.name = Some(Some(profile_name.to_string()));
+        profile.last_version_id = Some(Some(profile_version.to_string()));
+        profile.game_dir = profile_dir.map(|dir| Some(dir.to_string()));
+        profile.icon = profile_icon.map(|icon| Some(icon.to_string()));
+        if let (Some(java_args), true) = (java_args, overwrite_java_args) {
+            profile.java_args = Some(Some(java_args.to_string()));
+        }
+        Ok(())
+    }
+}
+
+pub async fn profiles_path() -> anyhow::Result<PathBuf> {
+    Ok(crate::get_launcher_path()
+        .await
+        .context("Could not determine launcher directory")?
+        .join("launcher_profiles.json"))
+}
+
+/// How often [`update`] re-applies its change when the file keeps changing underneath it.
+const UPDATE_ATTEMPTS: usize = 3;
+/// Number of backups of `launcher_profiles.json` kept next to it.
+const BACKUPS_KEPT: usize = 5;
+const BACKUP_PREFIX: &str = "launcher_profiles.paigaldaja-backup-";
+
+pub async fn read(path: &Path) -> anyhow::Result<LauncherProfiles> {
+    parse(path, &read_raw(path).await?)
+}
+
+async fn read_raw(path: &Path) -> anyhow::Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read launcher profiles at {}", path.display()))
+}
+
+fn parse(path: &Path, json: &str) -> anyhow::Result<LauncherProfiles> {
+    serde_json::from_str(json)
+        .with_context(|| format!("Launcher profiles at {} are malformed", path.display()))
+}
+
+/// Applies `change` to the launcher profiles and writes them back atomically,
+/// after backing up the previous version. If another program modifies the
+/// file in the meantime, the change is applied again to its new contents.
+pub async fn update(
+    path: &Path,
+    mut change: impl FnMut(&mut LauncherProfiles) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    for _ in 0..UPDATE_ATTEMPTS {
+        let original = read_raw(path).await?;
+        let mut profiles = parse(path, &original)?;
+        change(&mut profiles)?;
+        let json = serde_json::to_string(&profiles)?;
+        let tmp_path = path.with_extension("json.paigaldaja-tmp");
+        write_synced(&tmp_path, &json).await.with_context(|| {
+            format!(
+                "Failed to write launcher profiles at {}",
+                tmp_path.display()
+            )
+        })?;
+        if read_raw(path).await? != original {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            continue;
+        }
+        backup(path, &original).await?;
+        tokio::fs::rename(&tmp_path, path).await.with_context(|| {
+            format!("Failed to replace launcher profiles at {}", path.display())
+        })?;
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Launcher profiles at {} keep changing; close the Minecraft Launcher and try again",
+        path.display()
+    ))
+}
+
+async fn write_synced(path: &Path, contents: &str) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(contents.as_bytes()).await?;
+    file.sync_all().await
+}
+
+/// Saves `contents` as a timestamped copy next to `path`, removing the oldest
+/// copies beyond [`BACKUPS_KEPT`].
+async fn backup(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let dir = path
+        .parent()
+        .context("Launcher profiles have no parent directory")?;
+    let now = time::OffsetDateTime::now_utc();
+    let timestamp = format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+    tokio::fs::write(
+        dir.join(format!("{BACKUP_PREFIX}{timestamp}.json")),
+        contents,
+    )
+    .await
+    .context("Failed to back up launcher profiles")?;
+    let mut backups = vec![];
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .context("Failed to list launcher directory")?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Failed to list launcher directory")?
+    {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with(BACKUP_PREFIX)
+        {
+            backups.push(entry.path());
+        }
+    }
+    // timestamps sort chronologically
+    backups.sort();
+    for old in backups.iter().rev().skip(BACKUPS_KEPT) {
+        let _ = tokio::fs::remove_file(old).await;
+    }
+    Ok(())
+}
+
+/// Whether the Minecraft Launcher appears to be running. It rewrites
+/// `launcher_profiles.json` from memory, so changes made while it runs may be lost.
+pub async fn is_running() -> bool {
+    tauri::async_runtime::spawn_blocking(is_running_blocking)
+        .await
+        .unwrap_or(false)
+}
+
+fn is_running_blocking() -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        ["MinecraftLauncher.exe", "Minecraft.exe"]
+            .iter()
+            .any(|name| {
+                crate::background_command("tasklist")
+                    .args(["/NH", "/FI", &format!("IMAGENAME eq {name}")])
+                    .output()
+                    .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(name))
+            })
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        #[cfg(target_os = "macos")]
+        let pattern = "Minecraft.app/Contents/MacOS/launcher";
+        #[cfg(not(target_os = "macos"))]
+        let pattern = "minecraft-launcher";
+        std::process::Command::new("pgrep")
+            .args(["-f", pattern])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written by the Minecraft Launcher, plus keys it may add in the future.
+    const SAMPLE: &str = r#"{
+        "profiles": {
+            "fabulously-optimized": {
+                "created": "2023-06-01T12:00:00.000Z",
+                "gameDir": "/home/user/.minecraft/fo",
+                "icon": "Furnace",
+                "javaArgs": null,
+                "lastUsed": "2024-01-01T00:00:00.000Z",
+                "lastVersionId": "fabric-loader-0.15.11-1.20.1",
+                "name": "Fabulously Optimized",
+                "resolution": { "height": 480, "width": 854, "fullscreen": false },
+                "skipJreVersionCheck": true,
+                "type": "custom"
+            },
+            "latest-release": {
+                "created": "1970-01-02T00:00:00.000Z",
+                "icon": "Grass",
+                "lastUsed": "1970-01-02T00:00:00.000Z",
+                "lastVersionId": "latest-release",
+                "name": "",
+                "resolution": { "height": -1, "width": 854 },
+                "type": "latest-release"
+            }
+        },
+        "settings": {
+            "crashAssistance": true,
+            "enableAdvanced": false,
+            "enableAnalytics": true,
+            "enableHistorical": false,
+            "enableReleases": true,
+            "enableSnapshots": false,
+            "keepLauncherOpen": null,
+            "profileSorting": "ByLastPlayed",
+            "showGameLog": false,
+            "showMenu": false,
+            "soundOn": false
+        },
+        "version": 3
+    }"#;
+
+    fn round_trip(profiles: &LauncherProfiles) -> serde_json::Value {
+        serde_json::from_str(&serde_json::to_string(profiles).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip_is_lossless() {
+        let profiles = parse(Path::new("launcher_profiles.json"), SAMPLE).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(SAMPLE).unwrap();
+        assert_eq!(round_trip(&profiles), expected);
+    }
+
+    #[test]
+    fn updating_a_profile_keeps_other_fields() {
+        let mut profiles = parse(Path::new("launcher_profiles.json"), SAMPLE).unwrap();
+        profiles
+            .set_or_create_profile(
+                "fabulously-optimized",
+                "Fabulously Optimized",
+                Some("Furnace"),
+                "fabric-loader-0.16.0-1.20.1",
+                Some("/home/user/.minecraft/fo"),
+                Some("-Xmx4096M"),
+                false,
+            )
+            .unwrap();
+        let json = round_trip(&profiles);
+        let profile = &json["profiles"]["fabulously-optimized"];
+        assert_eq!(profile["lastVersionId"], "fabric-loader-0.16.0-1.20.1");
+        assert_eq!(profile["javaArgs"], serde_json::Value::Null);
+        assert!(profile.as_object().unwrap().contains_key("javaArgs"));
+        assert_eq!(profile["skipJreVersionCheck"], true);
+        assert_eq!(profile["resolution"]["fullscreen"], false);
+        assert_eq!(json["settings"]["profileSorting"], "ByLastPlayed");
+    }
+
+    #[test]
+    fn malformed_file_names_the_path() {
+        let e = parse(Path::new("launcher_profiles.json"), r#"{"profiles": []}"#).unwrap_err();
+        assert!(format!("{e:#}").contains("launcher_profiles.json"));
+    }
+}
@@ -5,7 +5,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use std::{
-    io::{Cursor, Read},
+    collections::HashSet,
     mem::ManuallyDrop,
     path::{Component, Path, PathBuf},
 };
@@ -14,19 +14,54 @@ use anyhow::{anyhow, Context};
 use mrpack::PackDependency;
 use reqwest::{StatusCode, Url};
 use sha2::Digest;
-use tauri::{api::process::Command, Manager};
+use tauri::Manager;
 use tempfile::tempdir;
-use tokio::{fs::File, io::AsyncWriteExt};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
 
+mod cancel;
 mod config;
+mod deps;
+mod flexver;
+mod instances;
+mod integrity;
+mod java;
+mod jvm;
+mod launcher;
+mod meta;
+mod mods;
 
 fn main() {
+    let config = config::load();
+    let bandwidth_limit = match net::bandwidth_limit_from_args() {
+        Ok(limit) => limit.or(config.network.bandwidth_limit),
+        Err(e) => {
+            eprintln!("{e:#}");
+            std::process::exit(2);
+        }
+    };
     tauri::Builder::default()
+        .manage(net::BandwidthLimiter::new(bandwidth_limit))
+        .manage(config)
+        .manage(cancel::ActiveInstall::default())
         .invoke_handler(tauri::generate_handler![
             install_mrpack,
+            cancel_install,
+            get_bandwidth_limit,
+            set_bandwidth_limit,
             get_installed_metadata,
             show_profile_dir_selector,
-            is_launcher_installed
+            is_launcher_installed,
+            verify_installation,
+            repair_installation,
+            list_unmanaged_mods,
+            get_memory_recommendation,
+            find_java_runtimes,
+            install_java_runtime,
+            list_instances,
+            check_for_updates
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -53,6 +88,11 @@ impl Drop for SelfCleanupFile<'_> {
 }
 
 mod mrpack;
+mod net;
+mod progress;
+mod repair;
+mod trust;
+mod updates;
 
 #[tauri::command]
 async fn show_profile_dir_selector() -> Option<PathBuf> {
@@ -114,24 +154,16 @@ async fn get_launcher_path() -> anyhow::Result<PathBuf> {
 }
 
 async fn install_fabriclike(
-    app_handle: &tauri::AppHandle,
     client: &reqwest::Client,
-    profile_url: String,
+    profile_url: &str,
     profile_name: &str,
 ) -> anyhow::Result<()> {
-    let mut profile_json = client
-        .get(profile_url)
-        .header(
-            "User-Agent",
-            format!(
-                "Paigaldaja/{} (+https://github.com/Fabulously-Optimized/vanilla-installer-rust)",
-                app_handle.package_info().version
-            ),
-        )
-        .send()
-        .await?;
+    let mut profile_json = client.get(profile_url).send().await?;
     if profile_json.status() != StatusCode::OK {
-        return Err(anyhow!("Metadata server did not respond with 200"));
+        return Err(net::status_error(
+            &profile_json,
+            "Metadata server did not respond with 200".to_string(),
+        ));
     }
     let versions_dir = get_launcher_path().await?.join("versions");
     let profile_dir = versions_dir.join(profile_name);
@@ -151,94 +183,228 @@ async fn install_fabriclike(
     Ok(())
 }
 
-fn set_or_create_profile(
-    json: &mut serde_json::Value,
-    profile_id: &str,
-    profile_name: &str,
-    profile_icon: Option<&str>,
-    profile_version: &str,
-    profile_dir: Option<&str>,
-) -> Option<()> {
-    let profiles: &mut serde_json::Map<String, serde_json::Value> =
-        json.as_object_mut()?.get_mut("profiles")?.as_object_mut()?;
-    let now = time::OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Iso8601::DEFAULT)
-        .ok()?;
-    let mut profile = serde_json::json!({
-        "name": profile_name,
-        "type": "custom",
-        "created": now,
-        "lastUsed": now,
-        "lastVersionId": profile_version,
-    });
-    if let Some(profile_dir) = profile_dir {
-        profile
-            .as_object_mut()
-            .unwrap()
-            .insert("gameDir".to_string(), profile_dir.into());
-    }
-    if let Some(profile_icon) = profile_icon {
-        profile
-            .as_object_mut()
-            .unwrap()
-            .insert("icon".to_string(), profile_icon.into());
-    }
-    profiles.insert(profile_id.to_string(), profile);
-    Some(())
+#[tauri::command]
+async fn get_installed_metadata(profile_dir: Option<String>) -> Option<serde_json::Value> {
+    read_installed_meta(&profile_dir)
+        .await
+        .map(|meta| meta.metadata)
 }
 
+async fn read_installed_meta(profile_dir: &Option<String>) -> Option<meta::InstallMetadata> {
+    let profile_path = canonicalize_profile_path(profile_dir).await.ok()?;
+    meta::read(&profile_path).await.ok().flatten()
+}
+
+/// Re-hashes the files of an installed pack and lists missing, modified and extra mods.
 #[tauri::command]
-async fn get_installed_metadata(profile_dir: Option<String>) -> Option<serde_json::Value> {
-    let meta_path = canonicalize_profile_path(&profile_dir)
+async fn verify_installation(
+    profile_dir: Option<String>,
+) -> Result<integrity::IntegrityReport, String> {
+    let installed = read_installed_meta(&profile_dir).await;
+    let (index, files) = installed
+        .as_ref()
+        .and_then(|meta| Some((meta.index.as_ref()?, &meta.files)))
+        .ok_or(
+            "No file hashes recorded for this installation; reinstall the pack to enable verification",
+        )?;
+    let profile_path = canonicalize_profile_path(&profile_dir)
         .await
-        .ok()?
-        .join("paigaldaja_meta.json");
-    let meta: serde_json::Value =
-        serde_json::from_str(&tokio::fs::read_to_string(meta_path).await.ok()?).ok()?;
-    if let serde_json::Value::Object(mut map) = meta {
-        map.remove("metadata")
-    } else {
-        None
+        .map_err(|e| format!("{e:#}"))?;
+    integrity::verify(&profile_path, index, files)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Lists jars in `mods/` that the installer didn't put there and the pack
+/// files they conflict with.
+#[tauri::command]
+async fn list_unmanaged_mods(
+    profile_dir: Option<String>,
+) -> Result<Vec<mods::UnmanagedMod>, String> {
+    let profile_path = canonicalize_profile_path(&profile_dir)
+        .await
+        .map_err(|e| format!("{e:#}"))?;
+    let managed = meta::read(&profile_path)
+        .await
+        .map_err(|e| format!("{e:#}"))?
+        .map(|meta| meta.files.into_iter().map(|file| file.path).collect())
+        .unwrap_or_default();
+    mods::find_unmanaged(&profile_path, &managed)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Suggests a heap size for the pack based on the system's memory.
+#[tauri::command]
+async fn get_memory_recommendation(extra_metadata: serde_json::Value) -> jvm::MemoryRecommendation {
+    jvm::recommend_memory(&extra_metadata).await
+}
+
+/// Lists installed Java runtimes and which of them suits `minecraft_version`.
+#[tauri::command]
+async fn find_java_runtimes(
+    app_handle: tauri::AppHandle,
+    minecraft_version: Option<String>,
+) -> Result<java::JavaReport, String> {
+    let config = app_handle.state::<config::Config>();
+    let client = net::build_client(&app_handle, &config.network).map_err(|e| format!("{e:#}"))?;
+    java::find_runtimes(&client, &config, minecraft_version.as_deref())
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Downloads the Java runtime Mojang ships for `minecraft_version`, for use
+/// with other launchers. Returns the path of its `java` executable.
+#[tauri::command]
+async fn install_java_runtime(
+    app_handle: tauri::AppHandle,
+    minecraft_version: String,
+) -> Result<PathBuf, String> {
+    let config = app_handle.state::<config::Config>();
+    config.ensure_loaded().map_err(|e| format!("{e:#}"))?;
+    let limiter = app_handle.state::<net::BandwidthLimiter>();
+    let client = net::build_client(&app_handle, &config.network).map_err(|e| format!("{e:#}"))?;
+    java::install_runtime(&client, &config, &limiter, &minecraft_version)
+        .await
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Lists every installation found in the default game directory, the
+/// instances directory and the game directories of the launcher's profiles,
+/// optionally only for one pack.
+#[tauri::command]
+async fn list_instances(pack_id: Option<String>) -> Result<Vec<instances::InstanceInfo>, String> {
+    let launcher_dir = get_launcher_path().await.map_err(|e| format!("{e:#}"))?;
+    let mut instances: Vec<_> = instances::list(&launcher_dir)
+        .await
+        .map_err(|e| format!("{e:#}"))?
+        .into_iter()
+        .map(|(game_dir, meta)| instances::InstanceInfo::new(game_dir, meta))
+        .collect();
+    if let Some(pack_id) = pack_id {
+        instances.retain(|instance| instance.pack_id.as_ref() == Some(&pack_id));
     }
+    Ok(instances)
 }
 
-async fn get_installed_files(profile_dir: &Option<String>) -> Option<Vec<String>> {
-    let meta_path = canonicalize_profile_path(profile_dir)
+/// Lists installations that have a newer version on Modrinth for the same
+/// Minecraft version.
+#[tauri::command]
+async fn check_for_updates(app_handle: tauri::AppHandle) -> Result<updates::UpdateReport, String> {
+    let config = app_handle.state::<config::Config>();
+    let client = net::build_client(&app_handle, &config.network).map_err(|e| format!("{e:#}"))?;
+    let launcher_dir = get_launcher_path().await.map_err(|e| format!("{e:#}"))?;
+    updates::check(&client, &config, &launcher_dir)
         .await
-        .ok()?
-        .join("paigaldaja_meta.json");
-    let meta: serde_json::Value =
-        serde_json::from_str(&tokio::fs::read_to_string(meta_path).await.ok()?).ok()?;
-    if let serde_json::Value::Object(mut map) = meta {
-        serde_json::from_value(map.remove("files")?).ok()
-    } else {
-        None
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Re-downloads missing or corrupted files of an installed pack and restores
+/// missing configuration files, loader version and launcher profile.
+#[tauri::command]
+async fn repair_installation(
+    app_handle: tauri::AppHandle,
+    active_install: tauri::State<'_, cancel::ActiveInstall>,
+    profile_dir: Option<String>,
+) -> Result<repair::RepairReport, String> {
+    let token = active_install
+        .begin()
+        .ok_or("Another install is already running")?;
+    let reporter = progress::Reporter::new(&app_handle);
+    // nothing to roll back: repairs only replace files that were already broken
+    let result = tokio::select! {
+        result = repair::repair(&app_handle, &reporter, &profile_dir) => result,
+        () = token.cancelled() => Err(cancel::Cancelled.into()),
+    };
+    if let Err(e) = &result {
+        if e.downcast_ref::<cancel::Cancelled>().is_some() {
+            reporter.cancelled();
+        } else {
+            reporter.fail(format!("{e:#}"));
+        }
     }
+    active_install.end();
+    result.map_err(|e| format!("{e:#}"))
 }
 
 #[tauri::command]
 async fn install_mrpack(
     app_handle: tauri::AppHandle,
+    active_install: tauri::State<'_, cancel::ActiveInstall>,
     url: String,
     pack_id: String,
     icon: Option<String>,
     pack_name: String,
     profile_dir: Option<String>,
     extra_metadata: serde_json::Value,
-    cosign_bundle_url: String,
+    cosign_bundle_url: Option<String>,
+    unmanaged_mods: Option<mods::UnmanagedModAction>,
+    apply_jvm_args: Option<bool>,
+    max_memory_mb: Option<u64>,
+    use_system_java: Option<bool>,
+    download_java: Option<bool>,
+    instance_name: Option<String>,
 ) -> Result<(), String> {
-    install_mrpack_inner(
-        app_handle,
-        url,
-        pack_id,
-        icon,
-        pack_name,
-        profile_dir,
-        extra_metadata,
-        cosign_bundle_url,
-    )
-    .await
-    .map_err(|e| format!("{e:#}"))
+    let token = active_install
+        .begin()
+        .ok_or("Another install is already running")?;
+    let reporter = progress::Reporter::new(&app_handle);
+    let journal = cancel::InstallJournal::default();
+    let result = tokio::select! {
+        result = install_mrpack_inner(
+            app_handle.clone(),
+            &reporter,
+            &token,
+            &journal,
+            url,
+            pack_id,
+            icon,
+            pack_name,
+            profile_dir,
+            extra_metadata,
+            cosign_bundle_url,
+            unmanaged_mods.unwrap_or_default(),
+            apply_jvm_args.unwrap_or(false),
+            max_memory_mb,
+            use_system_java.unwrap_or(false),
+            download_java.unwrap_or(false),
+            instance_name,
+        ) => result,
+        () = token.cancelled() => Err(cancel::Cancelled.into()),
+    };
+    if let Err(e) = &result {
+        journal.roll_back().await;
+        if e.downcast_ref::<cancel::Cancelled>().is_some() {
+            journal.discard_partial_downloads().await;
+            reporter.cancelled();
+        } else {
+            reporter.fail(format!("{e:#}"));
+        }
+    }
+    active_install.end();
+    result.map_err(|e| format!("{e:#}"))
+}
+
+/// Stops the running install, if it hasn't started writing the launcher
+/// profile yet. Returns whether there was an install to cancel.
+#[tauri::command]
+fn cancel_install(active_install: tauri::State<'_, cancel::ActiveInstall>) -> bool {
+    active_install.cancel()
+}
+
+/// Returns the combined download speed cap in bytes per second, if any.
+#[tauri::command]
+fn get_bandwidth_limit(limiter: tauri::State<'_, net::BandwidthLimiter>) -> Option<u64> {
+    limiter.limit()
+}
+
+/// Changes the download speed cap; takes effect immediately, also for a running install.
+#[tauri::command]
+fn set_bandwidth_limit(
+    limiter: tauri::State<'_, net::BandwidthLimiter>,
+    bytes_per_second: Option<u64>,
+) {
+    limiter.set_limit(bytes_per_second);
 }
 
 async fn canonicalize_profile_path(profile_dir: &Option<String>) -> anyhow::Result<PathBuf> {
@@ -253,17 +419,21 @@ async fn canonicalize_profile_path(profile_dir: &Option<String>) -> anyhow::Resu
     })
 }
 
-fn revalidate_url(url: &str, whitelist: &[&str]) -> anyhow::Result<Url> {
+fn revalidate_url(url: &str, whitelist: &[config::DomainRule]) -> anyhow::Result<Url> {
     let mut url = reqwest::Url::parse(url).context("Invalid URL!")?;
     let host = url.host_str().ok_or(anyhow!("URL doesn't have a host?"))?;
-    if !whitelist.contains(&host) {
-        return Err(anyhow!("Domain not allowed here: {}", host));
+    let rule = whitelist
+        .iter()
+        .find(|rule| rule.matches(host))
+        .ok_or_else(|| anyhow!("Domain not allowed here: {}", host))?;
+    if rule.allows_scheme(url.scheme()) {
+        return Ok(url);
     }
     if url.scheme() == "http" {
         url.set_scheme("https")
             .map_err(|()| anyhow!("Failed to upgrade HTTP to HTTPS!"))?;
     }
-    if url.scheme() != "https" {
+    if url.scheme() != "https" || !rule.allows_scheme("https") {
         return Err(anyhow!(
             "Weird scheme, possibly malicious: {}",
             url.scheme()
@@ -277,58 +447,218 @@ async fn try_download(
     client: &reqwest::Client,
     url: &str,
     path: &Path,
+    staging_dir: &Path,
     expected_hash: &[u8],
     expected_size: usize,
+    progress: &progress::DownloadProgress<'_>,
 ) -> anyhow::Result<()> {
-    let url = revalidate_url(url, config::DOWNLOADS_DOMAIN_WHITELIST)?;
-    let mut resp = client
-        .get(url)
-        .header(
-            "User-Agent",
-            format!(
-                "Paigaldaja/{} (+https://github.com/Fabulously-Optimized/vanilla-installer-rust)",
-                app_handle.package_info().version
-            ),
-        )
-        .send()
-        .await?;
-    if resp.status() != StatusCode::OK {
-        return Err(anyhow!("Status code was not 200, but {}", resp.status()));
+    let url = revalidate_url(url, &app_handle.state::<config::Config>().downloads_domains)?;
+    let limiter = app_handle.state::<net::BandwidthLimiter>();
+    // partial downloads are keyed by their hash, so they can be resumed from any mirror
+    let part_path = staging_dir.join(format!("{}.part", hex::encode(expected_hash)));
+    let (mut hasher, mut size) = read_partial_download(&part_path, expected_size).await;
+    progress.resume_file(size as u64);
+    if size == expected_size {
+        return finalize_download(&part_path, path, hasher, expected_hash, true).await;
     }
-    let mut size = 0usize;
-    let mut hasher = sha2::Sha512::new();
-    let mut file = SelfCleanupFile::new(path).await?;
+    let mut resumed = size > 0;
+    let mut request = client.get(url);
+    if size > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={size}-"));
+    }
+    let mut resp = request.send().await?;
+    let mut file = match resp.status() {
+        StatusCode::PARTIAL_CONTENT if size > 0 && content_range_start(&resp) == Some(size) => {
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&part_path)
+                .await?
+        }
+        StatusCode::OK => {
+            hasher = sha2::Sha512::new();
+            size = 0;
+            resumed = false;
+            progress.resume_file(0);
+            File::create(&part_path).await?
+        }
+        status => {
+            return Err(net::status_error(
+                &resp,
+                format!("Status code was not 200, but {}", status),
+            ))
+        }
+    };
     while let Some(chunk) = resp.chunk().await? {
         size += chunk.len();
         if size > expected_size {
+            drop(file);
+            let _ = tokio::fs::remove_file(&part_path).await;
             return Err(anyhow!(
                 "File is bigger than expected: expected {} bytes, aborting on {} bytes",
                 expected_size,
                 size
             ));
         }
+        limiter.throttle(chunk.len()).await;
         hasher.update(&chunk);
-        file.0.write_all(&chunk).await?;
+        file.write_all(&chunk).await?;
+        progress.add_bytes(chunk.len() as u64);
     }
+    file.flush().await?;
+    drop(file);
     if size < expected_size {
-        return Err(anyhow!(
+        // the connection was closed early; what we have is kept and resumed on retry
+        return Err(net::Transient::new(format!(
             "File is smaller than expected: expected {} bytes, got {} bytes",
-            expected_size,
-            size
-        ));
+            expected_size, size
+        ))
+        .into());
+    }
+    finalize_download(&part_path, path, hasher, expected_hash, resumed).await
+}
+
+/// Hashes what is already present of a partial download. Unreadable or
+/// oversized partial files are discarded.
+async fn read_partial_download(part_path: &Path, expected_size: usize) -> (sha2::Sha512, usize) {
+    let mut hasher = sha2::Sha512::new();
+    let Ok(mut file) = File::open(part_path).await else {
+        return (hasher, 0);
+    };
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0usize;
+    loop {
+        match file.read(&mut buf).await {
+            Ok(0) => return (hasher, size),
+            Ok(read) if size + read <= expected_size => {
+                size += read;
+                hasher.update(&buf[..read]);
+            }
+            _ => {
+                drop(file);
+                let _ = tokio::fs::remove_file(part_path).await;
+                return (sha2::Sha512::new(), 0);
+            }
+        }
     }
+}
+
+/// Moves a complete download into place if its hash matches. If part of it
+/// came from an earlier attempt, a mismatch is transient, as the partial file
+/// may have been corrupt and the retry starts from scratch.
+async fn finalize_download(
+    part_path: &Path,
+    path: &Path,
+    hasher: sha2::Sha512,
+    expected_hash: &[u8],
+    resumed: bool,
+) -> anyhow::Result<()> {
     let hash: [u8; 64] = hasher.finalize().into();
     if hash != expected_hash {
-        return Err(anyhow!(
+        let _ = tokio::fs::remove_file(part_path).await;
+        let message = format!(
             "Wrong hash: got {}, expected {}",
             hex::encode(hash),
             hex::encode(expected_hash)
+        );
+        if resumed {
+            return Err(net::Transient::new(message).into());
+        }
+        return Err(anyhow!(message));
+    }
+    tokio::fs::rename(part_path, path).await?;
+    Ok(())
+}
+
+fn content_range_start(resp: &reqwest::Response) -> Option<usize> {
+    let range = resp
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?;
+    range
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Streams the pack to `dest`, returning its SHA-256.
+async fn fetch_pack(
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    url: &str,
+    dest: &Path,
+) -> anyhow::Result<[u8; 32]> {
+    let url = revalidate_url(url, &config.pack_domains)?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .context("Failed to fetch modpack data")?;
+    if response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &response,
+            "Server did not respond with 200".to_string(),
         ));
     }
-    file.finalize();
+    let mut hasher = sha2::Sha256::new();
+    let mut file = File::create(dest)
+        .await
+        .context("Failed to store modpack data")?;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("Failed to fetch modpack data")?
+    {
+        limiter.throttle(chunk.len()).await;
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .await
+            .context("Failed to store modpack data")?;
+    }
+    file.flush().await.context("Failed to store modpack data")?;
+    Ok(hasher.finalize().into())
+}
+
+/// Copies one file out of the pack to `dest` without buffering it in memory.
+fn extract_override(
+    mrpack: &mut zip::ZipArchive<std::fs::File>,
+    name: &str,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let mut entry = mrpack
+        .by_name(name)
+        .context("Failed to read configuration file; corrupted mrpack?")?;
+    let mut file = std::fs::File::create(dest).with_context(|| {
+        format!(
+            "Failed to write configuration file at {}",
+            dest.to_string_lossy()
+        )
+    })?;
+    std::io::copy(&mut entry, &mut file).with_context(|| {
+        format!(
+            "Failed to write configuration file at {}",
+            dest.to_string_lossy()
+        )
+    })?;
     Ok(())
 }
 
+/// A command that doesn't open a console window on Windows.
+fn background_command(program: impl AsRef<std::ffi::OsStr>) -> std::process::Command {
+    #[cfg_attr(not(target_os = "windows"), allow(unused_mut))]
+    let mut command = std::process::Command::new(program);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
 fn parse_and_sanitize_path(path: &str) -> Option<&Path> {
     if path.contains('\0') {
         return None;
@@ -346,116 +676,37 @@ fn parse_and_sanitize_path(path: &str) -> Option<&Path> {
     Some(path)
 }
 
-async fn install_mrpack_inner(
-    app_handle: tauri::AppHandle,
-    url: String,
-    pack_id: String,
-    icon: Option<String>,
-    pack_name: String,
-    profile_dir: Option<String>,
-    extra_metadata: serde_json::Value,
-    cosign_bundle_url: String,
-) -> anyhow::Result<()> {
-    let profile_base_path = canonicalize_profile_path(&profile_dir)
+/// Downloads the pack from the first working mirror or URL, returning its SHA-256.
+async fn download_pack(
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    url: &str,
+    dest: &Path,
+) -> anyhow::Result<[u8; 32]> {
+    let mut last_err = None;
+    for url in config.with_mirrors(&[url.to_string()]) {
+        match net::with_retry(&config.retry, || {
+            fetch_pack(client, config, limiter, &url, dest)
+        })
         .await
-        .context("Could not determine profile directory")?;
-    let _ = app_handle.emit_all("install:progress", ("clean_old", "start"));
-    if let Some(files) = get_installed_files(&profile_dir).await {
-        for file in files {
-            // ignore Result as cleanup failing shouldn't abort install
-            let _ = tokio::fs::remove_file(profile_base_path.join(PathBuf::from(file))).await;
+        {
+            Ok(hash) => return Ok(hash),
+            Err(e) => {
+                last_err.replace(e);
+            }
         }
     }
-    let _ = app_handle.emit_all("install:progress", ("clean_old", "complete"));
-    let _ = app_handle.emit_all("install:progress", ("load_pack", "start"));
-    let mut written_files = vec![];
-    let url = revalidate_url(&url, config::PACK_DOMAIN_WHITELIST)?;
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .header(
-            "User-Agent",
-            format!(
-                "Paigaldaja/{} (+https://github.com/Fabulously-Optimized/vanilla-installer-rust)",
-                app_handle.package_info().version
-            ),
-        )
-        .send()
-        .await
-        .context("Failed to fetch modpack data")?;
-    if response.status() != StatusCode::OK {
-        return Err(anyhow!("Server did not respond with 200"));
-    }
-    let bytes = response
-        .bytes()
-        .await
-        .context("Failed to fetch modpack data")?;
-
-    let sig_response = client
-        .get(revalidate_url(
-            &cosign_bundle_url,
-            config::PACK_DOMAIN_WHITELIST,
-        )?)
-        .header(
-            "User-Agent",
-            format!(
-                "Paigaldaja/{} (+https://github.com/Fabulously-Optimized/vanilla-installer-rust)",
-                app_handle.package_info().version
-            ),
-        )
-        .send()
-        .await
-        .context("Failed to fetch modpack signature")?;
-    if sig_response.status() != StatusCode::OK {
-        return Err(anyhow!("Server did not respond with 200"));
-    }
-    let sig_bytes = sig_response
-        .bytes()
-        .await
-        .context("Failed to fetch modpack signature")?;
-    let mut cosign_bundle = zip::ZipArchive::new(Cursor::new(sig_bytes))
-        .context("Failed to parse modpack signature")?;
-    let mut buf = vec![];
-    let tempdir = tempdir().context("Couldn't acquire a temporary directory!")?;
-    {
-        let mut cosign_bundle = cosign_bundle
-            .by_name("cosign-bundle.json")
-            .context("Couldn't find signature in signature bundle!")?;
-        cosign_bundle
-            .read_to_end(&mut buf)
-            .context("Couldn't read signature bundle!")?;
-    }
-    let bundle_path = tempdir.path().join("cosign-bundle.json");
-    tokio::fs::write(&bundle_path, buf)
-        .await
-        .context("Couldn't read signature bundle!")?;
-    let hash: [u8; 32] = sha2::Sha256::digest(&bytes).into();
-    let hash = hex::encode(hash);
-    #[cfg(not(target_os = "windows"))]
-    let command = Command::new_sidecar("verifier").context("Couldn't verify signature!")?;
-    #[cfg(target_os = "windows")]
-    let command = Command::new({
-        let sidecar_path = tempdir.path().join("verifier.exe");
-        tokio::fs::write(
-            &sidecar_path,
-            include_bytes!("../../verifier/dist/verifier-x86_64-pc-windows-msvc.exe"),
-        )
-        .await
-        .context("Couldn't extract verifier!")?;
-        sidecar_path.to_string_lossy().to_string()
-    });
-    if !command
-        .args([&hash, bundle_path.to_string_lossy().as_ref()])
-        .status()
-        .context("Couldn't verify signature!")?
-        .success()
-    {
-        return Err(anyhow!("Couldn't verify signature!"));
-    }
-    _ = tempdir.close();
+    Err(last_err.unwrap())
+}
 
-    let mut mrpack =
-        zip::ZipArchive::new(Cursor::new(bytes)).context("Failed to parse modpack file")?;
+fn open_pack(
+    pack_path: &Path,
+) -> anyhow::Result<(zip::ZipArchive<std::fs::File>, mrpack::PackIndex)> {
+    let mut mrpack = zip::ZipArchive::new(
+        std::fs::File::open(pack_path).context("Failed to parse modpack file")?,
+    )
+    .context("Failed to parse modpack file")?;
     let index: mrpack::PackIndex = serde_json::from_reader(
         mrpack
             .by_name("modrinth.index.json")
@@ -468,73 +719,15 @@ async fn install_mrpack_inner(
     if index.game != "minecraft" {
         return Err(anyhow!("Unknown game {}", index.game));
     }
-    let _ = app_handle.emit_all("install:progress", ("load_pack", "complete"));
-    let _ = app_handle.emit_all(
-        "install:progress",
-        ("download_files", "start", index.files.len()),
-    );
-    for (i, file) in index.files.into_iter().enumerate() {
-        let _ = app_handle.emit_all(
-            "install:progress",
-            ("download_file", "start", i, &file.path),
-        );
-        let path = parse_and_sanitize_path(&file.path)
-            .ok_or(anyhow!("Possibly malicious download path: {}", file.path))?;
-        let path = profile_base_path.join(path);
-        if let Some(env) = file.env {
-            if let Some(&mrpack::SideType::Unsupported) = env.get(&mrpack::EnvType::Client) {
-                continue;
-            }
-        }
-        let hash = hex::decode(
-            file.hashes
-                .get(&mrpack::PackFileHash::Sha512)
-                .ok_or(anyhow!(
-                    "No SHA512 hash for file {}; This violates spec!",
-                    file.path
-                ))?,
-        )?;
-        if let Some(parent) = path.parent() {
-            tokio::fs::create_dir_all(profile_base_path.join(parent)).await?;
-        }
-        let mut success = false;
-        let mut last_err = None;
-        for url in file.downloads {
-            match try_download(
-                &app_handle,
-                &client,
-                &url,
-                &path,
-                &hash,
-                file.file_size as usize,
-            )
-            .await
-            {
-                Ok(()) => {
-                    written_files.push(path.to_owned());
-                    success = true;
-                    break;
-                }
-                Err(e) => {
-                    last_err.replace(e);
-                }
-            }
-        }
-        if !success {
-            return Err(anyhow!(
-                "Download failed for {}: {}",
-                file.path,
-                last_err.unwrap()
-            ));
-        }
-        let _ = app_handle.emit_all(
-            "install:progress",
-            ("download_file", "complete", i, &file.path),
-        );
-    }
-    let _ = app_handle.emit_all("install:progress", ("download_files", "complete"));
-    let _ = app_handle.emit_all("install:progress", ("extract_overrides", "start"));
+    Ok((mrpack, index))
+}
 
+/// Lists the overrides that apply to the client as pairs of zip entry name and
+/// destination path relative to the profile directory.
+fn override_entries(
+    mrpack: &mut zip::ZipArchive<std::fs::File>,
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut entries = vec![];
     for filename in mrpack
         .file_names()
         .map(|e| e.to_string())
@@ -545,51 +738,84 @@ async fn install_mrpack_inner(
         {
             continue;
         }
-        let mut buf: Vec<u8>;
-        let path: PathBuf;
-        {
-            let mut file = mrpack
-                .by_name(&filename)
-                .context("Failed to read configuration file; corrupted mrpack?")?;
-            if file.is_dir() {
-                continue;
-            }
-            let path_ref = file
-                .enclosed_name()
-                .ok_or(anyhow!("Possibly malicious config path: {}", file.name()))?;
-            path = if let Ok(path) = path_ref
-                .strip_prefix("overrides")
-                .or_else(|_| path_ref.strip_prefix("client-overrides"))
-                .map(Path::to_owned)
-            {
-                path
-            } else {
-                continue;
-            };
-            buf = vec![];
-            file.read_to_end(&mut buf)
-                .context("Failed to read configuration file; corrupted mrpack?")?;
+        let file = mrpack
+            .by_name(&filename)
+            .context("Failed to read configuration file; corrupted mrpack?")?;
+        if file.is_dir() {
+            continue;
         }
-        let abs_path = profile_base_path.join(&path);
-        if let Some(parent) = abs_path.parent() {
-            tokio::fs::create_dir_all(parent).await.with_context(|| {
-                format!(
-                    "Failed to create directories for configuration file at {}",
-                    path.to_string_lossy()
-                )
-            })?;
+        let path_ref = file
+            .enclosed_name()
+            .ok_or(anyhow!("Possibly malicious config path: {}", file.name()))?;
+        if let Ok(path) = path_ref
+            .strip_prefix("overrides")
+            .or_else(|_| path_ref.strip_prefix("client-overrides"))
+            .map(Path::to_owned)
+        {
+            entries.push((filename, path));
         }
-        tokio::fs::write(&abs_path, &buf).await.with_context(|| {
-            format!(
-                "Failed to write configuration file at {}",
-                abs_path.to_string_lossy()
+    }
+    Ok(entries)
+}
+
+/// Downloads one file from the index to `path`, trying mirrors and every listed
+/// URL in turn. Returns the URL the file was downloaded from.
+async fn download_pack_file(
+    app_handle: &tauri::AppHandle,
+    client: &reqwest::Client,
+    config: &config::Config,
+    file: &mrpack::PackFile,
+    path: &Path,
+    staging_dir: &Path,
+    progress: &progress::DownloadProgress<'_>,
+) -> anyhow::Result<String> {
+    let hash = hex::decode(
+        file.hashes
+            .get(&mrpack::PackFileHash::Sha512)
+            .ok_or(anyhow!(
+                "No SHA512 hash for file {}; This violates spec!",
+                file.path
+            ))?,
+    )?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut last_err = None;
+    for url in config.with_mirrors(&file.downloads) {
+        match net::with_retry(&config.retry, || {
+            try_download(
+                app_handle,
+                client,
+                &url,
+                path,
+                staging_dir,
+                &hash,
+                file.file_size as usize,
+                progress,
             )
-        })?;
-        written_files.push(path);
+        })
+        .await
+        {
+            Ok(()) => return Ok(url),
+            Err(e) => {
+                last_err.replace(e);
+            }
+        }
     }
+    Err(anyhow!(
+        "Download failed for {}: {}",
+        file.path,
+        last_err.unwrap()
+    ))
+}
 
-    let _ = app_handle.emit_all("install:progress", ("extract_overrides", "complete"));
-    let _ = app_handle.emit_all("install:progress", ("install_loader", "start"));
+/// Installs the mod loader version the pack depends on, returning the name of
+/// the launcher version to use.
+async fn install_loader(
+    client: &reqwest::Client,
+    config: &config::Config,
+    index: &mrpack::PackIndex,
+) -> anyhow::Result<String> {
     if index.dependencies.contains_key(&PackDependency::Forge) {
         return Err(anyhow!("Forge is currently unsupported"));
     }
@@ -604,57 +830,471 @@ async fn install_mrpack_inner(
             mc_version, fabric_version
         );
         version_name = format!("fabric-loader-{}-{}", fabric_version, mc_version);
-        install_fabriclike(&app_handle, &client, profile_url, &version_name)
-            .await
-            .context("Failed to install Fabric")?;
+        net::with_retry(&config.retry, || {
+            install_fabriclike(client, &profile_url, &version_name)
+        })
+        .await
+        .context("Failed to install Fabric")?;
     } else if let Some(quilt_version) = index.dependencies.get(&PackDependency::QuiltLoader) {
         let profile_url = format!(
             "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
             mc_version, quilt_version
         );
         version_name = format!("quilt-loader-{}-{}", quilt_version, mc_version);
-        install_fabriclike(&app_handle, &client, profile_url, &version_name)
-            .await
-            .context("Failed to install Quilt")?;
+        net::with_retry(&config.retry, || {
+            install_fabriclike(client, &profile_url, &version_name)
+        })
+        .await
+        .context("Failed to install Quilt")?;
     }
-    let _ = app_handle.emit_all("install:progress", ("install_loader", "complete"));
-    let _ = app_handle.emit_all("install:progress", ("add_profile", "start"));
-    let profiles_path = get_launcher_path()
+    Ok(version_name)
+}
+
+async fn add_launcher_profile(
+    reporter: &progress::Reporter,
+    pack_id: &str,
+    pack_name: &str,
+    icon: Option<&str>,
+    version_name: &str,
+    profile_dir: Option<&Path>,
+    java_args: Option<&str>,
+    overwrite_java_args: bool,
+    java_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    if launcher::is_running().await {
+        reporter.warn(
+            progress::Stage::AddProfile,
+            "The Minecraft Launcher is running; restart it if the installation doesn't show up"
+                .to_string(),
+        );
+    }
+    let profile_dir = profile_dir.map(|path| path.to_string_lossy());
+    launcher::update(&launcher::profiles_path().await?, |profiles| {
+        profiles
+            .set_or_create_profile(
+                pack_id,
+                pack_name,
+                icon,
+                version_name,
+                profile_dir.as_deref(),
+                java_args,
+                overwrite_java_args,
+            )
+            .context("Could not create launcher profile")?;
+        if let (Some(java_dir), Some(profile)) = (java_dir, profiles.profiles.get_mut(pack_id)) {
+            profile.java_dir = Some(Some(java_dir.to_string_lossy().to_string()));
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Picks the Java runtime for the profile: an installed one if allowed and
+/// compatible, otherwise one downloaded from Mojang if allowed. `None` leaves
+/// the choice to the launcher.
+async fn select_java(
+    reporter: &progress::Reporter,
+    client: &reqwest::Client,
+    config: &config::Config,
+    limiter: &net::BandwidthLimiter,
+    mc_version: &str,
+    use_system_java: bool,
+    download_java: bool,
+) -> anyhow::Result<Option<PathBuf>> {
+    if use_system_java {
+        let report = java::find_runtimes(client, config, Some(mc_version)).await?;
+        if let Some(runtime) = report.selected {
+            return Ok(Some(runtime.path));
+        }
+        if !download_java {
+            reporter.warn(
+                progress::Stage::InstallJava,
+                format!(
+                    "No installed Java {} or newer found; the launcher's bundled Java will be used",
+                    report.required_major_version.unwrap_or(8)
+                ),
+            );
+            return Ok(None);
+        }
+    }
+    java::install_runtime(client, config, limiter, mc_version)
         .await
-        .context("Could not determine profile directory")?
-        .join("launcher_profiles.json");
-    let mut profiles: serde_json::Value = serde_json::from_str(
-        &tokio::fs::read_to_string(&profiles_path)
-            .await
-            .context("Failed to read launcher profiles")?,
+        .map(Some)
+        .context("Failed to install Java")
+}
+
+/// Fails if the mods in the profile can't load together, and warns about
+/// problems that only involve mods the user added.
+async fn check_dependencies(
+    reporter: &progress::Reporter,
+    profile_dir: &Path,
+    managed: &HashSet<PathBuf>,
+    index: &mrpack::PackIndex,
+) -> anyhow::Result<()> {
+    let Some(mc_version) = index.dependencies.get(&PackDependency::Minecraft) else {
+        return Ok(());
+    };
+    let mut installed = vec![];
+    for jar in mods::list_jars(profile_dir).await? {
+        let path = jar.to_string_lossy().replace('\\', "/");
+        match mods::read_mod_tree(&profile_dir.join(&jar)) {
+            Ok(mods) => installed.push(deps::InstalledMod {
+                managed: managed.contains(&jar),
+                path,
+                mods,
+            }),
+            Err(e) => reporter.warn(
+                progress::Stage::CheckMods,
+                format!("Couldn't check dependencies of {path}: {e:#}"),
+            ),
+        }
+    }
+    let loader = match (
+        index.dependencies.get(&PackDependency::FabricLoader),
+        index.dependencies.get(&PackDependency::QuiltLoader),
+    ) {
+        (_, Some(version)) => Some(deps::Loader::Quilt(version)),
+        (Some(version), None) => Some(deps::Loader::Fabric(version)),
+        (None, None) => None,
+    };
+    let issues = deps::check(&installed, mc_version, loader);
+    let (fatal, warnings): (Vec<_>, Vec<_>) = issues.into_iter().partition(|issue| issue.fatal);
+    for issue in warnings {
+        reporter.warn(progress::Stage::CheckMods, issue.to_string());
+    }
+    if !fatal.is_empty() {
+        return Err(anyhow!(
+            "Mods can't be loaded together:\n{}",
+            fatal
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    Ok(())
+}
+
+async fn install_mrpack_inner(
+    app_handle: tauri::AppHandle,
+    reporter: &progress::Reporter,
+    token: &cancel::CancellationToken,
+    journal: &cancel::InstallJournal,
+    url: String,
+    pack_id: String,
+    icon: Option<String>,
+    pack_name: String,
+    profile_dir: Option<String>,
+    extra_metadata: serde_json::Value,
+    cosign_bundle_url: Option<String>,
+    unmanaged_mods: mods::UnmanagedModAction,
+    apply_jvm_args: bool,
+    max_memory_mb: Option<u64>,
+    use_system_java: bool,
+    download_java: bool,
+    instance_name: Option<String>,
+) -> anyhow::Result<()> {
+    let config = app_handle.state::<config::Config>();
+    config.ensure_loaded()?;
+    // a heap size the user picked applies to existing profiles too
+    let overwrite_java_args = apply_jvm_args || max_memory_mb.is_some();
+    let recommendation = jvm::recommend_memory(&extra_metadata).await;
+    let max_memory_mb = match max_memory_mb {
+        Some(max_memory_mb) => {
+            let total_mb = recommendation.total_mb.unwrap_or(u64::MAX);
+            if max_memory_mb < recommendation.minimum_mb.min(total_mb) || max_memory_mb > total_mb {
+                return Err(anyhow!(
+                    "A heap size of {} MB is not between the pack's minimum of {} MB and the system's memory",
+                    max_memory_mb,
+                    recommendation.minimum_mb
+                ));
+            }
+            max_memory_mb
+        }
+        None => {
+            if recommendation.low_memory {
+                reporter.warn(
+                    progress::Stage::AddProfile,
+                    format!(
+                        "The pack needs {} MB of memory, which leaves little for the rest of the system",
+                        recommendation.minimum_mb
+                    ),
+                );
+            }
+            recommendation.recommended_mb
+        }
+    };
+    let java_args = jvm::java_args(max_memory_mb, &extra_metadata);
+    // instances get a launcher profile and game directory of their own
+    let (profile_id, pack_name, profile_dir) = match &instance_name {
+        Some(name) => {
+            let instance = instances::resolve(&pack_id, &pack_name, name, profile_dir)?;
+            (
+                instance.profile_id,
+                instance.profile_name,
+                instance.profile_dir,
+            )
+        }
+        None => (pack_id.clone(), pack_name, profile_dir),
+    };
+    let profile_base_path = canonicalize_profile_path(&profile_dir)
+        .await
+        .context("Could not determine profile directory")?;
+    journal.set_profile_dir(&profile_base_path);
+    reporter.start(progress::Stage::CleanOld);
+    if let Some(installed) = read_installed_meta(&profile_dir).await {
+        for file in installed.files {
+            // ignore Result as cleanup failing shouldn't abort install
+            let _ = journal.set_aside(&profile_base_path.join(file.path)).await;
+        }
+    }
+    reporter.complete(progress::Stage::CleanOld);
+    reporter.start(progress::Stage::LoadPack);
+    let mut written_files = vec![];
+    let client = net::build_client(&app_handle, &config.network)?;
+    let limiter = app_handle.state::<net::BandwidthLimiter>();
+    let pack_dir = tempdir().context("Couldn't acquire a temporary directory!")?;
+    let pack_path = pack_dir.path().join("pack.mrpack");
+    let hash = download_pack(&client, &config, &limiter, &url, &pack_path).await?;
+    let pack_sha256 = hex::encode(hash);
+
+    let provenance = trust::verify_pack(
+        &app_handle,
+        reporter,
+        &client,
+        &pack_sha256,
+        cosign_bundle_url.as_deref(),
     )
-    .context("Failed to parse launcher profiles")?;
-    let profile_base_path_string = profile_base_path.to_string_lossy();
-    set_or_create_profile(
-        &mut profiles,
-        &pack_id,
+    .await?;
+
+    let (mut mrpack, index) = open_pack(&pack_path)?;
+    reporter.complete(progress::Stage::LoadPack);
+    let total_size = index
+        .files
+        .iter()
+        .filter(|file| !file.is_client_unsupported())
+        .map(|file| file.file_size as u64)
+        .sum();
+    let file_count = index.files.len();
+    let download_files_stage = progress::Stage::DownloadFiles {
+        file_count,
+        total_bytes: total_size,
+    };
+    reporter.start(download_files_stage.clone());
+    let progress = progress::DownloadProgress::new(reporter, file_count, total_size);
+    let staging_dir = profile_base_path.join(".paigaldaja_staging");
+    tokio::fs::create_dir_all(&staging_dir)
+        .await
+        .context("Failed to create download staging directory")?;
+    for (i, file) in index.files.iter().enumerate() {
+        let file_stage = progress::Stage::DownloadFile {
+            index: i,
+            path: file.path.clone(),
+        };
+        reporter.start(file_stage.clone());
+        let path = parse_and_sanitize_path(&file.path)
+            .ok_or(anyhow!("Possibly malicious download path: {}", file.path))?;
+        let path = profile_base_path.join(path);
+        if file.is_client_unsupported() {
+            continue;
+        }
+        progress.start_file(i, &file.path, file.file_size as u64);
+        journal
+            .set_aside(&path)
+            .await
+            .with_context(|| format!("Failed to back up {}", file.path))?;
+        let download_url = download_pack_file(
+            &app_handle,
+            &client,
+            &config,
+            file,
+            &path,
+            &staging_dir,
+            &progress,
+        )
+        .await?;
+        journal.wrote(&path);
+        written_files.push(meta::InstalledFile {
+            path: path.strip_prefix(&profile_base_path)?.to_owned(),
+            sha512: file.hashes.get(&mrpack::PackFileHash::Sha512).cloned(),
+            source: meta::FileSource::Download {
+                url: Some(download_url),
+            },
+        });
+        progress.finish_file();
+        reporter.complete(file_stage);
+    }
+    let _ = tokio::fs::remove_dir_all(&staging_dir).await;
+    reporter.complete(download_files_stage);
+    reporter.start(progress::Stage::ExtractOverrides);
+
+    for (filename, path) in override_entries(&mut mrpack)? {
+        let abs_path = profile_base_path.join(&path);
+        if let Some(parent) = abs_path.parent() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!(
+                    "Failed to create directories for configuration file at {}",
+                    path.to_string_lossy()
+                )
+            })?;
+        }
+        journal.set_aside(&abs_path).await.with_context(|| {
+            format!(
+                "Failed to back up configuration file at {}",
+                path.to_string_lossy()
+            )
+        })?;
+        extract_override(&mut mrpack, &filename, &abs_path)?;
+        journal.wrote(&abs_path);
+        written_files.push(meta::InstalledFile {
+            path,
+            sha512: None,
+            source: meta::FileSource::Override,
+        });
+    }
+
+    reporter.complete(progress::Stage::ExtractOverrides);
+    reporter.start(progress::Stage::CheckMods);
+    let managed: HashSet<PathBuf> = written_files.iter().map(|file| file.path.clone()).collect();
+    for unmanaged in mods::find_unmanaged(&profile_base_path, &managed).await? {
+        let disable = match unmanaged_mods {
+            mods::UnmanagedModAction::Keep => false,
+            mods::UnmanagedModAction::DisableConflicting => !unmanaged.conflicts_with.is_empty(),
+            mods::UnmanagedModAction::DisableAll => true,
+        };
+        if disable {
+            let disabled_path = mods::disable(&profile_base_path, &unmanaged.path).await?;
+            journal.disabled(&profile_base_path.join(&unmanaged.path), &disabled_path);
+            reporter.warn(
+                progress::Stage::CheckMods,
+                format!("Disabled {}, which is not part of the pack", unmanaged.path),
+            );
+        } else if !unmanaged.conflicts_with.is_empty() {
+            reporter.warn(
+                progress::Stage::CheckMods,
+                format!(
+                    "{} provides the same mod as {}",
+                    unmanaged.path,
+                    unmanaged.conflicts_with.join(", ")
+                ),
+            );
+        }
+    }
+    check_dependencies(reporter, &profile_base_path, &managed, &index).await?;
+    reporter.complete(progress::Stage::CheckMods);
+    reporter.start(progress::Stage::InstallLoader);
+    let version_name = install_loader(&client, &config, &index).await?;
+    reporter.complete(progress::Stage::InstallLoader);
+    let java_dir = match index.dependencies.get(&PackDependency::Minecraft) {
+        Some(mc_version) if use_system_java || download_java => {
+            reporter.start(progress::Stage::InstallJava);
+            let java_dir = select_java(
+                reporter,
+                &client,
+                &config,
+                &limiter,
+                mc_version,
+                use_system_java,
+                download_java,
+            )
+            .await?;
+            reporter.complete(progress::Stage::InstallJava);
+            java_dir
+        }
+        _ => None,
+    };
+    // catch unreadable launcher profiles while the install can still be cancelled
+    launcher::read(&launcher::profiles_path().await?).await?;
+    if !token.commit() {
+        return Err(cancel::Cancelled.into());
+    }
+    reporter.start(progress::Stage::AddProfile);
+    add_launcher_profile(
+        reporter,
+        &profile_id,
         &pack_name,
         icon.as_deref(),
         &version_name,
-        if profile_dir.is_some() {
-            Some(&profile_base_path_string)
-        } else {
-            None
-        },
+        profile_dir.as_ref().map(|_| profile_base_path.as_path()),
+        Some(&java_args),
+        overwrite_java_args,
+        java_dir.as_deref(),
     )
-    .ok_or(anyhow!("Could not create launcher profile"))?;
-    tokio::fs::write(profiles_path, serde_json::to_string(&profiles)?)
-        .await
-        .context("Failed to write launcher profiles")?;
-    tokio::fs::write(
-        profile_base_path.join("paigaldaja_meta.json"),
-        serde_json::to_string(&serde_json::json!({
-            "files": written_files,
-            "metadata": extra_metadata
-        }))?,
+    .await?;
+    let installed_at = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Iso8601::DEFAULT)
+        .ok();
+    meta::write(
+        &profile_base_path,
+        &meta::InstallMetadata {
+            format_version: meta::FORMAT_VERSION,
+            pack_id: Some(pack_id),
+            pack_name: Some(index.name.clone()),
+            instance: instance_name,
+            version_id: Some(index.version_id.clone()),
+            minecraft_version: index.dependencies.get(&PackDependency::Minecraft).cloned(),
+            loader: meta::LoaderInfo::from_index(&index),
+            launcher_version: Some(version_name),
+            installed_at,
+            installer_version: Some(app_handle.package_info().version.to_string()),
+            source: Some(meta::PackSource {
+                url,
+                sha256: pack_sha256,
+                trust: provenance,
+            }),
+            profile: Some(meta::ProfileInfo {
+                id: profile_id,
+                name: pack_name,
+                icon,
+            }),
+            files: written_files,
+            index: Some(index),
+            metadata: extra_metadata,
+        },
     )
-    .await
-    .context("Failed to write installer metadata")?;
-    let _ = app_handle.emit_all("install:progress", ("add_profile", "complete"));
+    .await?;
+    journal.commit().await;
+    reporter.complete(progress::Stage::AddProfile);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(json: &str) -> Vec<config::DomainRule> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn revalidate_url_checks_domains() {
+        let rules = rules(r#"["*.example.com", "cdn.modrinth.com"]"#);
+        assert!(revalidate_url("https://cdn.example.com/a.jar", &rules).is_ok());
+        assert!(revalidate_url("https://evilexample.com/a.jar", &rules).is_err());
+        assert!(revalidate_url("https://example.com/a.jar", &rules).is_err());
+        assert!(revalidate_url("https://cdn.modrinth.com.evil.net/a.jar", &rules).is_err());
+        assert!(revalidate_url("not a url", &rules).is_err());
+    }
+
+    #[test]
+    fn revalidate_url_upgrades_http() {
+        let rules = rules(r#"["cdn.modrinth.com"]"#);
+        let url = revalidate_url("http://cdn.modrinth.com/a.jar", &rules).unwrap();
+        assert_eq!(url.as_str(), "https://cdn.modrinth.com/a.jar");
+        assert!(revalidate_url("ftp://cdn.modrinth.com/a.jar", &rules).is_err());
+    }
+
+    #[test]
+    fn revalidate_url_honors_rule_schemes() {
+        let http_only = rules(r#"[{ "domain": "mirror.internal", "schemes": ["http"] }]"#);
+        assert!(revalidate_url("https://mirror.internal/a.jar", &http_only).is_err());
+        let url = revalidate_url("http://mirror.internal/a.jar", &http_only).unwrap();
+        assert_eq!(url.scheme(), "http");
+
+        let both = rules(r#"[{ "domain": "mirror.internal", "schemes": ["http", "https"] }]"#);
+        let url = revalidate_url("http://mirror.internal/a.jar", &both).unwrap();
+        assert_eq!(url.as_str(), "http://mirror.internal/a.jar");
+        let url = revalidate_url("https://mirror.internal/a.jar", &both).unwrap();
+        assert_eq!(url.scheme(), "https");
+    }
+}
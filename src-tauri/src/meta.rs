@@ -0,0 +1,174 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::mrpack::{self, PackDependency};
+
+pub const META_FILE_NAME: &str = "paigaldaja_meta.json";
+
+/// Bumped whenever the layout of [`InstallMetadata`] changes incompatibly.
+/// Files written before versioning was introduced have no version at all.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Contents of `paigaldaja_meta.json`, written to the profile directory after
+/// every install. Fields that older installers didn't record are `None`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstallMetadata {
+    pub format_version: u32,
+    pub pack_id: Option<String>,
+    pub pack_name: Option<String>,
+    /// Name of the instance, if the pack is installed more than once.
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// `versionId` from `modrinth.index.json`.
+    pub version_id: Option<String>,
+    pub minecraft_version: Option<String>,
+    pub loader: Option<LoaderInfo>,
+    /// Name of the version created in the launcher's `versions` folder.
+    pub launcher_version: Option<String>,
+    pub installed_at: Option<String>,
+    pub installer_version: Option<String>,
+    pub source: Option<PackSource>,
+    pub profile: Option<ProfileInfo>,
+    pub files: Vec<InstalledFile>,
+    pub index: Option<mrpack::PackIndex>,
+    /// Opaque data passed in by the frontend.
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LoaderInfo {
+    pub kind: PackDependency,
+    pub version: String,
+}
+
+impl LoaderInfo {
+    pub fn from_index(index: &mrpack::PackIndex) -> Option<Self> {
+        [PackDependency::FabricLoader, PackDependency::QuiltLoader]
+            .into_iter()
+            .find_map(|kind| {
+                Some(Self {
+                    kind,
+                    version: index.dependencies.get(&kind)?.clone(),
+                })
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PackSource {
+    pub url: String,
+    pub sha256: String,
+    pub trust: crate::trust::Provenance,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProfileInfo {
+    /// Key of the profile in `launcher_profiles.json`.
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstalledFile {
+    /// Relative to the profile directory.
+    pub path: PathBuf,
+    pub sha512: Option<String>,
+    pub source: FileSource,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileSource {
+    /// Downloaded from `url`, which is `None` for installs that predate recording it.
+    Download { url: Option<String> },
+    /// Extracted from the pack's overrides.
+    Override,
+}
+
+/// Layout used before [`FORMAT_VERSION`] 1: downloaded files were recorded by
+/// absolute path, overrides relative to the profile directory. Nothing else
+/// about the install was recorded.
+#[derive(Deserialize)]
+struct LegacyMetadata {
+    #[serde(default)]
+    files: Vec<PathBuf>,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+impl LegacyMetadata {
+    fn migrate(self, profile_dir: &Path) -> InstallMetadata {
+        let files = self
+            .files
+            .into_iter()
+            .map(|path| match path.strip_prefix(profile_dir) {
+                Ok(relative) if path.is_absolute() => InstalledFile {
+                    path: relative.to_owned(),
+                    sha512: None,
+                    source: FileSource::Download { url: None },
+                },
+                _ => InstalledFile {
+                    path,
+                    sha512: None,
+                    source: FileSource::Override,
+                },
+            })
+            .collect();
+        InstallMetadata {
+            format_version: FORMAT_VERSION,
+            pack_id: None,
+            pack_name: None,
+            instance: None,
+            version_id: None,
+            minecraft_version: None,
+            loader: None,
+            launcher_version: None,
+            installed_at: None,
+            installer_version: None,
+            source: None,
+            profile: None,
+            files,
+            index: None,
+            metadata: self.metadata,
+        }
+    }
+}
+
+/// Reads the metadata of the installation in `profile_dir`, upgrading older
+/// formats in memory. Returns `None` if nothing is installed there.
+pub async fn read(profile_dir: &Path) -> anyhow::Result<Option<InstallMetadata>> {
+    let json = match tokio::fs::read_to_string(profile_dir.join(META_FILE_NAME)).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read installer metadata"),
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(&json).context("Installer metadata is invalid")?;
+    let meta = match value.get("format_version").and_then(|v| v.as_u64()) {
+        None => serde_json::from_value::<LegacyMetadata>(value)
+            .context("Installer metadata is invalid")?
+            .migrate(profile_dir),
+        Some(version) if version == FORMAT_VERSION as u64 => {
+            serde_json::from_value(value).context("Installer metadata is invalid")?
+        }
+        Some(version) => {
+            return Err(anyhow::anyhow!(
+                "Installer metadata version {} is newer than this installer supports",
+                version
+            ))
+        }
+    };
+    Ok(Some(meta))
+}
+
+pub async fn write(profile_dir: &Path, meta: &InstallMetadata) -> anyhow::Result<()> {
+    tokio::fs::write(
+        profile_dir.join(META_FILE_NAME),
+        serde_json::to_string(meta)?,
+    )
+    .await
+    .context("Failed to write installer metadata")
+}
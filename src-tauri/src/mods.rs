@@ -0,0 +1,359 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// A jar in `mods/` that wasn't installed by the installer.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmanagedMod {
+    /// Relative to the profile directory.
+    pub path: String,
+    /// `None` if the jar has no Fabric or Quilt metadata.
+    pub mod_id: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Pack files that provide the same mod id.
+    pub conflicts_with: Vec<String>,
+}
+
+/// What to do with unmanaged mods when installing or updating a pack.
+#[derive(Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmanagedModAction {
+    /// Leave them in place and only warn about conflicts.
+    #[default]
+    Keep,
+    /// Disable the ones that share a mod id with a pack mod.
+    DisableConflicting,
+    /// Disable all of them.
+    DisableAll,
+}
+
+/// Version requirements by mod id. Each requirement is a list of alternatives,
+/// of which every one is a space-separated list of version predicates.
+pub type Relations = HashMap<String, Vec<String>>;
+
+/// The parts of a jar's `fabric.mod.json` or `quilt.mod.json` the installer uses.
+/// Relations are only read from `fabric.mod.json`.
+#[derive(Clone, Debug)]
+pub struct ModMetadata {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: String,
+    pub provides: Vec<String>,
+    pub depends: Relations,
+    pub breaks: Relations,
+    pub conflicts: Relations,
+}
+
+impl ModMetadata {
+    /// The mod's own id followed by the ids it provides.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.id.as_str()).chain(self.provides.iter().map(String::as_str))
+    }
+}
+
+#[derive(Deserialize)]
+struct FabricModJson {
+    id: String,
+    version: String,
+    name: Option<String>,
+    #[serde(default)]
+    provides: Vec<String>,
+    #[serde(default)]
+    depends: HashMap<String, OneOrMany>,
+    #[serde(default)]
+    breaks: HashMap<String, OneOrMany>,
+    #[serde(default)]
+    conflicts: HashMap<String, OneOrMany>,
+    #[serde(default)]
+    jars: Vec<FabricNestedJar>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Vec<String> {
+    fn from(value: OneOrMany) -> Self {
+        match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+fn relations(json: HashMap<String, OneOrMany>) -> Relations {
+    json.into_iter()
+        .map(|(id, versions)| (id, versions.into()))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct FabricNestedJar {
+    file: String,
+}
+
+#[derive(Deserialize)]
+struct QuiltModJson {
+    quilt_loader: QuiltLoader,
+}
+
+#[derive(Deserialize)]
+struct QuiltLoader {
+    id: String,
+    version: String,
+    #[serde(default)]
+    metadata: QuiltMetadata,
+    #[serde(default)]
+    provides: Vec<QuiltProvides>,
+    #[serde(default)]
+    jars: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct QuiltMetadata {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuiltProvides {
+    Id(String),
+    Object { id: String },
+}
+
+impl From<FabricModJson> for ModMetadata {
+    fn from(json: FabricModJson) -> Self {
+        Self {
+            id: json.id,
+            name: json.name,
+            version: json.version,
+            provides: json.provides,
+            depends: relations(json.depends),
+            breaks: relations(json.breaks),
+            conflicts: relations(json.conflicts),
+        }
+    }
+}
+
+impl From<QuiltModJson> for ModMetadata {
+    fn from(json: QuiltModJson) -> Self {
+        let loader = json.quilt_loader;
+        Self {
+            id: loader.id,
+            name: loader.metadata.name,
+            version: loader.version,
+            provides: loader
+                .provides
+                .into_iter()
+                .map(|provides| match provides {
+                    QuiltProvides::Id(id) | QuiltProvides::Object { id } => id,
+                })
+                .collect(),
+            depends: Relations::new(),
+            breaks: Relations::new(),
+            conflicts: Relations::new(),
+        }
+    }
+}
+
+/// Reads the Quilt or Fabric metadata of a jar, preferring Quilt's like Quilt
+/// Loader does. Returns `None` for jars that have neither.
+pub fn read_mod_metadata(jar: &Path) -> anyhow::Result<Option<ModMetadata>> {
+    let mut archive = open_jar(jar)?;
+    Ok(read_archive_metadata(&mut archive, &jar.to_string_lossy())?.map(|(metadata, _)| metadata))
+}
+
+/// Like [`read_mod_metadata`], but also reads the mods nested in the jar,
+/// which the loader loads as well.
+pub fn read_mod_tree(jar: &Path) -> anyhow::Result<Vec<ModMetadata>> {
+    let mut mods = vec![];
+    read_archive_tree(&mut open_jar(jar)?, &jar.to_string_lossy(), &mut mods)?;
+    Ok(mods)
+}
+
+fn open_jar(jar: &Path) -> anyhow::Result<zip::ZipArchive<std::fs::File>> {
+    zip::ZipArchive::new(
+        std::fs::File::open(jar)
+            .with_context(|| format!("Failed to open {}", jar.to_string_lossy()))?,
+    )
+    .with_context(|| format!("{} is not a valid jar", jar.to_string_lossy()))
+}
+
+fn read_archive_tree<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+    mods: &mut Vec<ModMetadata>,
+) -> anyhow::Result<()> {
+    let Some((metadata, nested)) = read_archive_metadata(archive, name)? else {
+        return Ok(());
+    };
+    mods.push(metadata);
+    for nested_name in nested {
+        let mut jar = vec![];
+        archive
+            .by_name(&nested_name)
+            .and_then(|mut entry| Ok(entry.read_to_end(&mut jar)?))
+            .with_context(|| format!("Failed to read {nested_name} in {name}"))?;
+        let mut nested_archive = zip::ZipArchive::new(std::io::Cursor::new(jar))
+            .with_context(|| format!("{nested_name} in {name} is not a valid jar"))?;
+        read_archive_tree(&mut nested_archive, &nested_name, mods)?;
+    }
+    Ok(())
+}
+
+/// Returns the metadata of the jar and the names of the jars nested in it.
+fn read_archive_metadata<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> anyhow::Result<Option<(ModMetadata, Vec<String>)>> {
+    if let Some(json) = read_entry(archive, "quilt.mod.json")? {
+        let json: QuiltModJson = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid quilt.mod.json in {name}"))?;
+        let nested = json.quilt_loader.jars.clone();
+        return Ok(Some((json.into(), nested)));
+    }
+    if let Some(json) = read_entry(archive, "fabric.mod.json")? {
+        let mut json: FabricModJson = serde_json::from_str(&json)
+            .with_context(|| format!("Invalid fabric.mod.json in {name}"))?;
+        let nested = std::mem::take(&mut json.jars)
+            .into_iter()
+            .map(|jar| jar.file)
+            .collect();
+        return Ok(Some((json.into(), nested)));
+    }
+    Ok(None)
+}
+
+fn read_entry<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> anyhow::Result<Option<String>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {name}")),
+    };
+    let mut json = String::new();
+    entry
+        .read_to_string(&mut json)
+        .with_context(|| format!("Failed to read {name}"))?;
+    Ok(Some(json))
+}
+
+/// Lists the jars directly in `mods/`, relative to the profile directory.
+pub async fn list_jars(profile_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut jars = vec![];
+    let mut entries = match tokio::fs::read_dir(profile_dir.join("mods")).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(jars),
+        Err(e) => return Err(e).context("Failed to list mods folder"),
+    };
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Failed to list mods folder")?
+    {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "jar") && path.is_file() {
+            jars.push(Path::new("mods").join(entry.file_name()));
+        }
+    }
+    jars.sort();
+    Ok(jars)
+}
+
+/// Identifies the jars in `mods/` that aren't in `managed` and checks them for
+/// mod ids that a managed jar also provides.
+pub async fn find_unmanaged(
+    profile_dir: &Path,
+    managed: &HashSet<PathBuf>,
+) -> anyhow::Result<Vec<UnmanagedMod>> {
+    let mut provided_by: HashMap<String, Vec<String>> = HashMap::new();
+    let mut unmanaged = vec![];
+    for jar in list_jars(profile_dir).await? {
+        // jars without readable metadata can't conflict by id, so they aren't fatal
+        let metadata = read_mod_metadata(&profile_dir.join(&jar)).ok().flatten();
+        let path = jar.to_string_lossy().replace('\\', "/");
+        if managed.contains(&jar) {
+            for id in metadata.iter().flat_map(ModMetadata::ids) {
+                provided_by
+                    .entry(id.to_string())
+                    .or_default()
+                    .push(path.clone());
+            }
+        } else {
+            unmanaged.push((path, metadata));
+        }
+    }
+    Ok(unmanaged
+        .into_iter()
+        .map(|(path, metadata)| {
+            let mut conflicts_with: Vec<String> = metadata
+                .iter()
+                .flat_map(ModMetadata::ids)
+                .filter_map(|id| provided_by.get(id))
+                .flatten()
+                .cloned()
+                .collect();
+            conflicts_with.sort();
+            conflicts_with.dedup();
+            UnmanagedMod {
+                path,
+                mod_id: metadata.as_ref().map(|metadata| metadata.id.clone()),
+                name: metadata.as_ref().and_then(|metadata| metadata.name.clone()),
+                version: metadata.map(|metadata| metadata.version),
+                conflicts_with,
+            }
+        })
+        .collect())
+}
+
+/// Renames a jar so the loader no longer picks it up, returning the new path.
+/// An earlier disabled copy of the jar is kept by picking a new name.
+pub async fn disable(profile_dir: &Path, path: &str) -> anyhow::Result<PathBuf> {
+    let from = profile_dir.join(path);
+    let mut to = profile_dir.join(format!("{path}.disabled"));
+    let mut n = 1;
+    while tokio::fs::symlink_metadata(&to).await.is_ok() {
+        n += 1;
+        to = profile_dir.join(format!("{path}.{n}.disabled"));
+    }
+    tokio::fs::rename(&from, &to)
+        .await
+        .with_context(|| format!("Failed to disable {path}"))?;
+    Ok(to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn disable_keeps_earlier_disabled_copies() {
+        let profile_dir = tempfile::tempdir().unwrap();
+        let mods_dir = profile_dir.path().join("mods");
+        std::fs::create_dir(&mods_dir).unwrap();
+        std::fs::write(mods_dir.join("a.jar.disabled"), "old").unwrap();
+        std::fs::write(mods_dir.join("a.jar"), "new").unwrap();
+
+        let disabled = disable(profile_dir.path(), "mods/a.jar").await.unwrap();
+
+        assert_eq!(disabled, mods_dir.join("a.jar.2.disabled"));
+        assert_eq!(std::fs::read_to_string(&disabled).unwrap(), "new");
+        assert_eq!(
+            std::fs::read_to_string(mods_dir.join("a.jar.disabled")).unwrap(),
+            "old"
+        );
+        assert!(!mods_dir.join("a.jar").exists());
+    }
+}
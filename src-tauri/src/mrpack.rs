@@ -22,8 +22,17 @@ pub struct PackFile {
     pub file_size: u32,
 }
 
+impl PackFile {
+    pub fn is_client_unsupported(&self) -> bool {
+        matches!(
+            self.env.as_ref().and_then(|env| env.get(&EnvType::Client)),
+            Some(SideType::Unsupported)
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
-#[serde(rename_all = "camelCase", from = "String")]
+#[serde(from = "String", into = "String")]
 pub enum PackFileHash {
     Sha1,
     Sha512,
@@ -40,6 +49,16 @@ impl From<String> for PackFileHash {
     }
 }
 
+impl From<PackFileHash> for String {
+    fn from(hash: PackFileHash) -> Self {
+        match hash {
+            PackFileHash::Sha1 => "sha1".to_string(),
+            PackFileHash::Sha512 => "sha512".to_string(),
+            PackFileHash::Unknown(s) => s,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub enum EnvType {
@@ -0,0 +1,280 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+
+/// Network settings applied to every request the installer makes.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL used for all requests.
+    pub proxy: Option<String>,
+    pub proxy_username: Option<String>,
+    pub proxy_password: Option<String>,
+    /// PEM or DER encoded certificates trusted in addition to the system roots.
+    pub extra_root_certificates: Vec<std::path::PathBuf>,
+    pub timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    /// Combined download speed cap in bytes per second; can be overridden with
+    /// `--bandwidth-limit` or from the frontend.
+    pub bandwidth_limit: Option<u64>,
+}
+
+pub fn build_client(
+    app_handle: &tauri::AppHandle,
+    config: &NetworkConfig,
+) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().user_agent(format!(
+        "Paigaldaja/{} (+https://github.com/Fabulously-Optimized/vanilla-installer-rust)",
+        app_handle.package_info().version
+    ));
+    if let Some(proxy_url) = &config.proxy {
+        let mut proxy = reqwest::Proxy::all(proxy_url).context("Invalid proxy URL")?;
+        match (&config.proxy_username, &config.proxy_password) {
+            (Some(username), Some(password)) => proxy = proxy.basic_auth(username, password),
+            (None, None) => (),
+            _ => return Err(anyhow!("Proxy username and password must be set together")),
+        }
+        builder = builder.proxy(proxy);
+    }
+    for path in &config.extra_root_certificates {
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read certificate {}", path.to_string_lossy()))?;
+        let cert = reqwest::Certificate::from_pem(&data)
+            .or_else(|_| reqwest::Certificate::from_der(&data))
+            .with_context(|| format!("Invalid certificate {}", path.to_string_lossy()))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if let Some(secs) = config.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = config.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    builder.build().context("Failed to set up HTTP client")
+}
+
+/// How often and how patiently failed requests are retried. Only transient
+/// failures (connection problems, timeouts and statuses like 429 or 503) are
+/// retried; a hash or size mismatch fails immediately.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the backoff. A `Retry-After` longer than this is not
+    /// waited for and the request fails instead.
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        let max = self.max_backoff_ms as f64;
+        let base = (self.initial_backoff_ms as f64 * 2f64.powi(attempt as i32 - 1)).min(max);
+        // "equal jitter": wait at least half the backoff so retries still spread out
+        Duration::from_millis((base / 2.0 + base / 2.0 * jitter()) as u64)
+    }
+}
+
+/// A uniformly distributed value in `[0, 1)`, seeded from the standard library's
+/// per-hasher random keys to avoid pulling in an RNG for this alone.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Marks an error as transient, i.e. the same request may succeed later.
+#[derive(Debug)]
+pub struct Transient {
+    message: String,
+    retry_after: Option<Duration>,
+}
+
+impl Transient {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            retry_after: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Transient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Transient {}
+
+/// Turns an unexpected response status into an error, marking it as transient
+/// if the server is likely to recover.
+pub fn status_error(resp: &reqwest::Response, message: String) -> anyhow::Error {
+    use reqwest::StatusCode;
+    match resp.status() {
+        StatusCode::REQUEST_TIMEOUT
+        | StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::INTERNAL_SERVER_ERROR
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => {
+            // only the delay-seconds form of Retry-After is supported
+            let retry_after = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+            Transient {
+                message,
+                retry_after,
+            }
+            .into()
+        }
+        _ => anyhow!(message),
+    }
+}
+
+/// Returns `Some` with the server-requested delay, if any, when the error is worth retrying.
+fn retry_delay(e: &anyhow::Error) -> Option<Option<Duration>> {
+    if let Some(transient) = e.downcast_ref::<Transient>() {
+        return Some(transient.retry_after);
+    }
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout() || e.is_connect() || e.is_request() || e.is_body())
+        .then_some(None)
+}
+
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut f: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        let e = match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        let Some(retry_after) = retry_delay(&e) else {
+            return Err(e);
+        };
+        if attempt >= policy.max_attempts {
+            return Err(e);
+        }
+        let delay = match retry_after {
+            Some(delay) if delay > Duration::from_millis(policy.max_backoff_ms) => return Err(e),
+            Some(delay) => delay,
+            None => policy.backoff(attempt),
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// A token bucket shared by all downloads, so the cap applies to their
+/// combined throughput. A limit of zero means unlimited.
+pub struct BandwidthLimiter {
+    limit: AtomicU64,
+    bucket: tokio::sync::Mutex<Bucket>,
+}
+
+struct Bucket {
+    available: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit: AtomicU64::new(limit.unwrap_or(0)),
+            bucket: tokio::sync::Mutex::new(Bucket {
+                available: 0.0,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        Some(self.limit.load(Ordering::Relaxed)).filter(|&limit| limit > 0)
+    }
+
+    pub fn set_limit(&self, limit: Option<u64>) {
+        self.limit.store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Waits until `bytes` more bytes may be read. Chunks bigger than the
+    /// bucket put it into debt, which later callers wait out.
+    pub async fn throttle(&self, bytes: usize) {
+        let Some(limit) = self.limit() else {
+            return;
+        };
+        let limit = limit as f64;
+        // waiting while holding the lock makes concurrent downloads queue up in order
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.last_refill).as_secs_f64() * limit;
+        // allow bursts of up to one second worth of data
+        bucket.available = (bucket.available + refill).min(limit) - bytes as f64;
+        bucket.last_refill = now;
+        if bucket.available < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-bucket.available / limit)).await;
+        }
+    }
+}
+
+/// Parses a bandwidth like `500000`, `800K` or `2M` (binary multiples) into bytes per second.
+pub fn parse_bandwidth(value: &str) -> anyhow::Result<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&value[..i], 1024),
+        Some((i, 'm' | 'M')) => (&value[..i], 1024 * 1024),
+        _ => (value, 1),
+    };
+    let number: u64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid bandwidth limit: {value}"))?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow!("Bandwidth limit too large: {value}"))
+}
+
+/// Reads `--bandwidth-limit <value>` or `--bandwidth-limit=<value>` from the command line.
+pub fn bandwidth_limit_from_args() -> anyhow::Result<Option<u64>> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = if arg == "--bandwidth-limit" {
+            args.next()
+                .ok_or_else(|| anyhow!("--bandwidth-limit needs a value"))?
+        } else if let Some(value) = arg.strip_prefix("--bandwidth-limit=") {
+            value.to_string()
+        } else {
+            continue;
+        };
+        return parse_bandwidth(&value).map(Some);
+    }
+    Ok(None)
+}
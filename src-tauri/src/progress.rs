@@ -0,0 +1,245 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tauri::Manager;
+
+/// Bumped whenever a field is removed or changes meaning, so frontends can
+/// detect events they don't understand. Adding fields or variants does not
+/// bump the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Payload of every `install:progress` event.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub stage: Stage,
+    #[serde(flatten)]
+    pub status: Status,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(
+    tag = "stage",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum Stage {
+    /// Only used when repairing an installation.
+    VerifyFiles,
+    CleanOld,
+    LoadPack,
+    DownloadFiles {
+        file_count: usize,
+        total_bytes: u64,
+    },
+    DownloadFile {
+        index: usize,
+        path: String,
+    },
+    ExtractOverrides,
+    /// Looks for mods the user added that conflict with the pack.
+    CheckMods,
+    InstallLoader,
+    /// Only used when the profile gets a Java runtime other than the launcher's.
+    InstallJava,
+    AddProfile,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(
+    tag = "status",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum Status {
+    Start,
+    Progress {
+        bytes_done: u64,
+        bytes_total: u64,
+        /// Only reported for single files.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bytes_per_second: Option<u64>,
+    },
+    Complete,
+    /// Something the user should know about that doesn't stop the install.
+    Warning {
+        message: String,
+    },
+    /// The install stopped during this stage.
+    Error {
+        message: String,
+    },
+    /// The install was cancelled during this stage and its files were removed.
+    Cancelled,
+}
+
+/// Emits progress events and remembers the current stage, so that a failure
+/// can be attributed to it.
+pub struct Reporter {
+    app_handle: tauri::AppHandle,
+    current: Mutex<Option<Stage>>,
+}
+
+impl Reporter {
+    pub fn new(app_handle: &tauri::AppHandle) -> Self {
+        Self {
+            app_handle: app_handle.clone(),
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn emit(&self, stage: Stage, status: Status) {
+        let _ = self.app_handle.emit_all(
+            "install:progress",
+            ProgressEvent {
+                schema_version: SCHEMA_VERSION,
+                stage,
+                status,
+            },
+        );
+    }
+
+    pub fn start(&self, stage: Stage) {
+        *self.current.lock().unwrap() = Some(stage.clone());
+        self.emit(stage, Status::Start);
+    }
+
+    pub fn complete(&self, stage: Stage) {
+        self.emit(stage, Status::Complete);
+    }
+
+    pub fn warn(&self, stage: Stage, message: String) {
+        self.emit(stage, Status::Warning { message });
+    }
+
+    /// Reports an error against the most recently started stage.
+    pub fn fail(&self, message: String) {
+        let stage = self.current.lock().unwrap().clone();
+        self.emit(stage.unwrap_or(Stage::CleanOld), Status::Error { message });
+    }
+
+    /// Reports cancellation against the most recently started stage.
+    pub fn cancelled(&self) {
+        let stage = self.current.lock().unwrap().clone();
+        self.emit(stage.unwrap_or(Stage::CleanOld), Status::Cancelled);
+    }
+}
+
+/// Tracks bytes written during the `download_files` stage and emits throttled
+/// progress events for the current file and the stage as a whole.
+pub struct DownloadProgress<'a> {
+    reporter: &'a Reporter,
+    file_count: usize,
+    total_size: u64,
+    state: Mutex<State>,
+}
+
+struct State {
+    index: usize,
+    path: String,
+    file_size: u64,
+    file_bytes: u64,
+    completed_bytes: u64,
+    resumed_bytes: u64,
+    file_started: Instant,
+    last_emit: Option<Instant>,
+}
+
+impl<'a> DownloadProgress<'a> {
+    pub fn new(reporter: &'a Reporter, file_count: usize, total_size: u64) -> Self {
+        Self {
+            reporter,
+            file_count,
+            total_size,
+            state: Mutex::new(State {
+                index: 0,
+                path: String::new(),
+                file_size: 0,
+                file_bytes: 0,
+                completed_bytes: 0,
+                resumed_bytes: 0,
+                file_started: Instant::now(),
+                last_emit: None,
+            }),
+        }
+    }
+
+    pub fn start_file(&self, index: usize, path: &str, file_size: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.index = index;
+        state.path = path.to_string();
+        state.file_size = file_size;
+        state.file_bytes = 0;
+        state.resumed_bytes = 0;
+        state.file_started = Instant::now();
+        state.last_emit = None;
+    }
+
+    /// Called at the start of every download attempt with the number of bytes
+    /// already present, so resumed bytes don't count towards throughput.
+    pub fn resume_file(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.file_bytes = bytes;
+        state.resumed_bytes = bytes;
+        state.file_started = Instant::now();
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.file_bytes += bytes;
+        let now = Instant::now();
+        if state
+            .last_emit
+            .is_some_and(|last| now.duration_since(last) < EMIT_INTERVAL)
+        {
+            return;
+        }
+        state.last_emit = Some(now);
+        self.emit(&state);
+    }
+
+    pub fn finish_file(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.file_bytes = state.file_size;
+        self.emit(&state);
+        state.completed_bytes += state.file_size;
+    }
+
+    fn emit(&self, state: &State) {
+        let elapsed = state.file_started.elapsed().as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 {
+            ((state.file_bytes - state.resumed_bytes) as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        self.reporter.emit(
+            Stage::DownloadFile {
+                index: state.index,
+                path: state.path.clone(),
+            },
+            Status::Progress {
+                bytes_done: state.file_bytes,
+                bytes_total: state.file_size,
+                bytes_per_second: Some(bytes_per_second),
+            },
+        );
+        self.reporter.emit(
+            Stage::DownloadFiles {
+                file_count: self.file_count,
+                total_bytes: self.total_size,
+            },
+            Status::Progress {
+                bytes_done: state.completed_bytes + state.file_bytes,
+                bytes_total: self.total_size,
+                bytes_per_second: None,
+            },
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use tauri::Manager;
+use tempfile::tempdir;
+
+use crate::{config, integrity, launcher, meta, mrpack, net, progress};
+
+/// What [`repair`] had to fix. Paths are relative to the profile directory.
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    pub redownloaded_files: Vec<String>,
+    pub restored_overrides: Vec<String>,
+    pub reinstalled_loader: bool,
+    pub recreated_profile: bool,
+}
+
+/// Restores an installation to the state recorded in its metadata, fetching
+/// only what is missing or corrupted. Files that are still correct, including
+/// edited configuration files, are left alone.
+pub async fn repair(
+    app_handle: &tauri::AppHandle,
+    reporter: &progress::Reporter,
+    profile_dir: &Option<String>,
+) -> anyhow::Result<RepairReport> {
+    let config = app_handle.state::<config::Config>();
+    config.ensure_loaded()?;
+    let profile_base_path = crate::canonicalize_profile_path(profile_dir)
+        .await
+        .context("Could not determine profile directory")?;
+    let installed = meta::read(&profile_base_path)
+        .await?
+        .ok_or(anyhow!("No installation found in this profile"))?;
+    let index = installed.index.as_ref().ok_or(anyhow!(
+        "No file hashes recorded for this installation; reinstall the pack to repair it"
+    ))?;
+    let client = net::build_client(app_handle, &config.network)?;
+    let mut report = RepairReport::default();
+
+    reporter.start(progress::Stage::VerifyFiles);
+    let integrity = integrity::verify(&profile_base_path, index, &installed.files).await?;
+    let broken: Vec<&mrpack::PackFile> = index
+        .files
+        .iter()
+        .filter(|file| {
+            integrity.missing.contains(&file.path) || integrity.modified.contains(&file.path)
+        })
+        .collect();
+    let missing_overrides: Vec<&PathBuf> = installed
+        .files
+        .iter()
+        .filter(|file| {
+            file.source == meta::FileSource::Override
+                && !profile_base_path.join(&file.path).exists()
+        })
+        .map(|file| &file.path)
+        .collect();
+    reporter.complete(progress::Stage::VerifyFiles);
+
+    if !integrity.is_intact() {
+        let total_size = broken.iter().map(|file| file.file_size as u64).sum();
+        let download_files_stage = progress::Stage::DownloadFiles {
+            file_count: broken.len(),
+            total_bytes: total_size,
+        };
+        reporter.start(download_files_stage.clone());
+        let progress = progress::DownloadProgress::new(reporter, broken.len(), total_size);
+        let staging_dir = profile_base_path.join(".paigaldaja_staging");
+        tokio::fs::create_dir_all(&staging_dir)
+            .await
+            .context("Failed to create download staging directory")?;
+        for (i, file) in broken.into_iter().enumerate() {
+            let file_stage = progress::Stage::DownloadFile {
+                index: i,
+                path: file.path.clone(),
+            };
+            reporter.start(file_stage.clone());
+            let path = crate::parse_and_sanitize_path(&file.path)
+                .ok_or(anyhow!("Possibly malicious download path: {}", file.path))?;
+            let path = profile_base_path.join(path);
+            progress.start_file(i, &file.path, file.file_size as u64);
+            crate::download_pack_file(
+                app_handle,
+                &client,
+                &config,
+                file,
+                &path,
+                &staging_dir,
+                &progress,
+            )
+            .await?;
+            progress.finish_file();
+            reporter.complete(file_stage);
+            report.redownloaded_files.push(file.path.clone());
+        }
+        let _ = tokio::fs::remove_dir_all(&staging_dir).await;
+        reporter.complete(download_files_stage);
+    }
+
+    if !missing_overrides.is_empty() {
+        let Some(meta::PackSource {
+            url: pack_url,
+            sha256: pack_sha256,
+            ..
+        }) = &installed.source
+        else {
+            return Err(anyhow!(
+                "Configuration files are missing, but the modpack they came from wasn't recorded; reinstall the pack instead"
+            ));
+        };
+        reporter.start(progress::Stage::LoadPack);
+        let limiter = app_handle.state::<net::BandwidthLimiter>();
+        let pack_dir = tempdir().context("Couldn't acquire a temporary directory!")?;
+        let pack_path = pack_dir.path().join("pack.mrpack");
+        let hash = crate::download_pack(&client, &config, &limiter, pack_url, &pack_path).await?;
+        // the recorded hash was verified when the pack was installed
+        if !hex::encode(hash).eq_ignore_ascii_case(pack_sha256) {
+            return Err(anyhow!(
+                "Modpack at {} changed since it was installed; reinstall the pack instead",
+                pack_url
+            ));
+        }
+        let (mut mrpack, _) = crate::open_pack(&pack_path)?;
+        reporter.complete(progress::Stage::LoadPack);
+        reporter.start(progress::Stage::ExtractOverrides);
+        for (filename, path) in crate::override_entries(&mut mrpack)? {
+            if !missing_overrides.contains(&&path) {
+                continue;
+            }
+            let abs_path = profile_base_path.join(&path);
+            if let Some(parent) = abs_path.parent() {
+                tokio::fs::create_dir_all(parent).await.with_context(|| {
+                    format!(
+                        "Failed to create directories for configuration file at {}",
+                        path.to_string_lossy()
+                    )
+                })?;
+            }
+            crate::extract_override(&mut mrpack, &filename, &abs_path)?;
+            report
+                .restored_overrides
+                .push(path.to_string_lossy().to_string());
+        }
+        reporter.complete(progress::Stage::ExtractOverrides);
+    }
+
+    reporter.start(progress::Stage::InstallLoader);
+    let versions_dir = crate::get_launcher_path().await?.join("versions");
+    let loader_missing = match &installed.launcher_version {
+        Some(name) => !versions_dir
+            .join(name)
+            .join(format!("{name}.json"))
+            .is_file(),
+        None => false,
+    };
+    let mut version_name = installed.launcher_version.clone();
+    if loader_missing {
+        version_name = Some(crate::install_loader(&client, &config, index).await?);
+        report.reinstalled_loader = true;
+    }
+    reporter.complete(progress::Stage::InstallLoader);
+
+    if let (Some(profile), Some(version_name)) = (&installed.profile, &version_name) {
+        reporter.start(progress::Stage::AddProfile);
+        let profiles = launcher::read(&launcher::profiles_path().await?).await?;
+        if !profiles.profiles.contains_key(&profile.id) {
+            crate::add_launcher_profile(
+                reporter,
+                &profile.id,
+                &profile.name,
+                profile.icon.as_deref(),
+                version_name,
+                profile_dir.as_ref().map(|_| profile_base_path.as_path()),
+                None,
+                false,
+                None,
+            )
+            .await?;
+            report.recreated_profile = true;
+        }
+        reporter.complete(progress::Stage::AddProfile);
+    }
+    Ok(report)
+}
@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tauri::{api::process::Command, Manager};
+use tempfile::tempdir;
+use tokio::io::AsyncWriteExt;
+
+use crate::{config, net, progress};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignaturePolicy {
+    /// Every pack must come with a valid cosign bundle or have a pinned digest.
+    Require,
+    /// Packs without a cosign bundle are installed after emitting a warning.
+    /// A bundle that is present but invalid is still rejected.
+    WarnIfMissing,
+}
+
+/// Identity the verifier requires of the signing certificate; see `verifier/main.go`.
+pub const SIGNER_IDENTITY: &str = "https://github.com/Fabulously-Optimized/fabulously-optimized";
+pub const SIGNER_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+/// Why a pack was allowed to be installed.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Provenance {
+    /// The cosign bundle was verified against the given signer.
+    Signed { identity: String, issuer: String },
+    /// The pack's digest is pinned in [`config::TRUSTED_PACK_SHA256`].
+    Pinned,
+    /// Installed without a signature under [`SignaturePolicy::WarnIfMissing`].
+    Unsigned,
+}
+
+fn is_pinned(pack_sha256: &str) -> bool {
+    config::TRUSTED_PACK_SHA256
+        .iter()
+        .any(|digest| digest.eq_ignore_ascii_case(pack_sha256))
+}
+
+/// Decides whether a pack with the given SHA-256 may be installed, verifying
+/// its cosign bundle if one is provided.
+pub async fn verify_pack(
+    app_handle: &tauri::AppHandle,
+    reporter: &progress::Reporter,
+    client: &reqwest::Client,
+    pack_sha256: &str,
+    cosign_bundle_url: Option<&str>,
+) -> anyhow::Result<Provenance> {
+    if is_pinned(pack_sha256) {
+        reporter.warn(
+            progress::Stage::LoadPack,
+            format!("Skipped signature check for pinned modpack {pack_sha256}"),
+        );
+        return Ok(Provenance::Pinned);
+    }
+    match (cosign_bundle_url, config::SIGNATURE_POLICY) {
+        (Some(url), _) => {
+            verify_cosign_bundle(app_handle, client, url, pack_sha256).await?;
+            Ok(Provenance::Signed {
+                identity: SIGNER_IDENTITY.to_string(),
+                issuer: SIGNER_ISSUER.to_string(),
+            })
+        }
+        (None, SignaturePolicy::Require) => Err(anyhow!(
+            "Modpack is not signed and its hash {} is not trusted",
+            pack_sha256
+        )),
+        (None, SignaturePolicy::WarnIfMissing) => {
+            reporter.warn(
+                progress::Stage::LoadPack,
+                format!("Modpack {pack_sha256} is not signed"),
+            );
+            Ok(Provenance::Unsigned)
+        }
+    }
+}
+
+async fn verify_cosign_bundle(
+    app_handle: &tauri::AppHandle,
+    client: &reqwest::Client,
+    cosign_bundle_url: &str,
+    pack_sha256: &str,
+) -> anyhow::Result<()> {
+    let config = app_handle.state::<config::Config>();
+    let tempdir = tempdir().context("Couldn't acquire a temporary directory!")?;
+    let bundle_zip_path = tempdir.path().join("cosign-bundle.zip");
+    let mut fetched = false;
+    let mut last_err = None;
+    for url in config.with_mirrors(&[cosign_bundle_url.to_string()]) {
+        match net::with_retry(&config.retry, || {
+            fetch_cosign_bundle(client, &config, &url, &bundle_zip_path)
+        })
+        .await
+        {
+            Ok(()) => {
+                fetched = true;
+                break;
+            }
+            Err(e) => {
+                last_err.replace(e);
+            }
+        }
+    }
+    if !fetched {
+        return Err(last_err.unwrap());
+    }
+    let bundle_path = tempdir.path().join("cosign-bundle.json");
+    {
+        let mut cosign_bundle = zip::ZipArchive::new(
+            std::fs::File::open(&bundle_zip_path).context("Failed to parse modpack signature")?,
+        )
+        .context("Failed to parse modpack signature")?;
+        let mut cosign_bundle = cosign_bundle
+            .by_name("cosign-bundle.json")
+            .context("Couldn't find signature in signature bundle!")?;
+        let mut bundle_file =
+            std::fs::File::create(&bundle_path).context("Couldn't read signature bundle!")?;
+        std::io::copy(&mut cosign_bundle, &mut bundle_file)
+            .context("Couldn't read signature bundle!")?;
+    }
+    #[cfg(not(target_os = "windows"))]
+    let command = Command::new_sidecar("verifier").context("Couldn't verify signature!")?;
+    #[cfg(target_os = "windows")]
+    let command = Command::new({
+        let sidecar_path = tempdir.path().join("verifier.exe");
+        tokio::fs::write(
+            &sidecar_path,
+            include_bytes!("../../verifier/dist/verifier-x86_64-pc-windows-msvc.exe"),
+        )
+        .await
+        .context("Couldn't extract verifier!")?;
+        sidecar_path.to_string_lossy().to_string()
+    });
+    if !command
+        .args([pack_sha256, bundle_path.to_string_lossy().as_ref()])
+        .status()
+        .context("Couldn't verify signature!")?
+        .success()
+    {
+        return Err(anyhow!("Couldn't verify signature!"));
+    }
+    _ = tempdir.close();
+    Ok(())
+}
+
+async fn fetch_cosign_bundle(
+    client: &reqwest::Client,
+    config: &config::Config,
+    url: &str,
+    dest: &Path,
+) -> anyhow::Result<()> {
+    let mut sig_response = client
+        .get(crate::revalidate_url(url, &config.pack_domains)?)
+        .send()
+        .await
+        .context("Failed to fetch modpack signature")?;
+    if sig_response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &sig_response,
+            "Server did not respond with 200".to_string(),
+        ));
+    }
+    let mut file = tokio::fs::File::create(dest)
+        .await
+        .context("Failed to store modpack signature")?;
+    while let Some(chunk) = sig_response
+        .chunk()
+        .await
+        .context("Failed to fetch modpack signature")?
+    {
+        file.write_all(&chunk)
+            .await
+            .context("Failed to store modpack signature")?;
+    }
+    file.flush()
+        .await
+        .context("Failed to store modpack signature")?;
+    Ok(())
+}
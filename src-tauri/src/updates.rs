@@ -0,0 +1,329 @@
+use std::{cmp::Ordering, collections::HashMap, path::PathBuf};
+
+use anyhow::Context;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{config, flexver, instances, net};
+
+/// A newer version of an installed pack.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub game_dir: PathBuf,
+    pub instance: Option<String>,
+    pub pack_id: Option<String>,
+    pub project_id: String,
+    pub minecraft_version: String,
+    pub installed_version: String,
+    pub latest_version: String,
+    /// Modrinth's id of the latest version.
+    pub version_id: String,
+    pub url: String,
+    pub cosign_bundle_url: Option<String>,
+}
+
+/// An installation whose updates couldn't be looked up.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedCheck {
+    pub game_dir: PathBuf,
+    pub instance: Option<String>,
+    pub pack_id: Option<String>,
+    pub error: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReport {
+    pub updates: Vec<UpdateInfo>,
+    pub failed: Vec<FailedCheck>,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    id: String,
+    version_number: String,
+    files: Vec<VersionFile>,
+}
+
+#[derive(Deserialize)]
+struct VersionFile {
+    url: String,
+    primary: bool,
+    filename: String,
+}
+
+/// Extracts the project id from a Modrinth CDN URL like
+/// `https://cdn.modrinth.com/data/<project>/versions/<version>/<file>`.
+fn project_id(pack_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(pack_url).ok()?;
+    let mut segments = url.path_segments()?;
+    (segments.next()? == "data").then_some(())?;
+    let project_id = segments.next()?;
+    (segments.next()? == "versions").then(|| project_id.to_string())
+}
+
+async fn list_versions(
+    client: &reqwest::Client,
+    config: &config::Config,
+    project_id: &str,
+    minecraft_version: &str,
+) -> anyhow::Result<Vec<Version>> {
+    let mut url = reqwest::Url::parse(&format!(
+        "{}/project/{}/version",
+        config.modrinth_api.trim_end_matches('/'),
+        project_id
+    ))
+    .context("Invalid Modrinth API URL")?;
+    url.query_pairs_mut()
+        .append_pair("include_changelog", "false")
+        .append_pair(
+            "game_versions",
+            &serde_json::to_string(&[minecraft_version])?,
+        );
+    let response = client.get(url).send().await?;
+    if response.status() != StatusCode::OK {
+        return Err(net::status_error(
+            &response,
+            "Modrinth did not respond with 200".to_string(),
+        ));
+    }
+    Ok(serde_json::from_slice(&response.bytes().await?)?)
+}
+
+/// The newest of `versions` if it is newer than `installed`, by FlexVer ordering.
+fn newest_after<'a>(versions: &'a [Version], installed: &str) -> Option<&'a Version> {
+    versions
+        .iter()
+        .max_by(|a, b| flexver::compare(&a.version_number, &b.version_number))
+        .filter(|latest| flexver::compare(&latest.version_number, installed) == Ordering::Greater)
+}
+
+/// Looks up newer versions for every installation that was installed from
+/// Modrinth, staying on the Minecraft version it was installed for. A failed
+/// lookup only affects the installations of that project.
+pub async fn check(
+    client: &reqwest::Client,
+    config: &config::Config,
+    launcher_dir: &std::path::Path,
+) -> anyhow::Result<UpdateReport> {
+    let mut versions: HashMap<(String, String), Result<Vec<Version>, String>> = HashMap::new();
+    let mut report = UpdateReport::default();
+    for (game_dir, meta) in instances::list(launcher_dir).await? {
+        let (Some(source), Some(minecraft_version), Some(installed_version)) =
+            (&meta.source, meta.minecraft_version, meta.version_id)
+        else {
+            continue;
+        };
+        let Some(project_id) = project_id(&source.url) else {
+            continue;
+        };
+        let key = (project_id.clone(), minecraft_version.clone());
+        if !versions.contains_key(&key) {
+            let found = net::with_retry(&config.retry, || {
+                list_versions(client, config, &project_id, &minecraft_version)
+            })
+            .await
+            .with_context(|| format!("Failed to list versions of {project_id}"))
+            .map_err(|e| format!("{e:#}"));
+            versions.insert(key.clone(), found);
+        }
+        let found = match &versions[&key] {
+            Ok(found) => found,
+            Err(e) => {
+                report.failed.push(FailedCheck {
+                    game_dir,
+                    instance: meta.instance,
+                    pack_id: meta.pack_id,
+                    error: e.clone(),
+                });
+                continue;
+            }
+        };
+        let Some(latest) = newest_after(found, &installed_version) else {
+            continue;
+        };
+        let Some(primary) = latest.files.iter().find(|file| file.primary) else {
+            continue;
+        };
+        report.updates.push(UpdateInfo {
+            game_dir,
+            instance: meta.instance,
+            pack_id: meta.pack_id,
+            project_id,
+            minecraft_version,
+            installed_version,
+            latest_version: latest.version_number.clone(),
+            version_id: latest.id.clone(),
+            url: primary.url.clone(),
+            cosign_bundle_url: latest
+                .files
+                .iter()
+                .find(|file| file.filename == "cosign-bundle.zip")
+                .map(|file| file.url.clone()),
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        path::Path,
+    };
+
+    use super::*;
+
+    fn version(id: &str, version_number: &str) -> Version {
+        Version {
+            id: id.to_string(),
+            version_number: version_number.to_string(),
+            files: vec![],
+        }
+    }
+
+    #[test]
+    fn project_id_from_cdn_url() {
+        assert_eq!(
+            project_id("https://cdn.modrinth.com/data/1KVo5zza/versions/abcd1234/pack.mrpack")
+                .as_deref(),
+            Some("1KVo5zza")
+        );
+        assert_eq!(
+            project_id("https://cdn.modrinth.com/other/1KVo5zza/versions/a/b"),
+            None
+        );
+        assert_eq!(project_id("https://example.com/pack.mrpack"), None);
+        assert_eq!(project_id("not a url"), None);
+    }
+
+    #[test]
+    fn newest_after_uses_flexver() {
+        let versions = [
+            version("a", "5.0.0"),
+            version("b", "5.10.0"),
+            version("c", "5.9.0-beta.1"),
+        ];
+        assert_eq!(
+            newest_after(&versions, "5.0.0").map(|v| v.id.as_str()),
+            Some("b")
+        );
+        assert!(newest_after(&versions, "5.10.0").is_none());
+        assert!(newest_after(&[], "5.0.0").is_none());
+    }
+
+    /// Serves canned responses for `/project/{id}/version` until the test ends.
+    fn mock_api(projects: &'static [(&'static str, &'static str)]) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = vec![];
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let body = projects.iter().find_map(|(id, body)| {
+                    path.starts_with(&format!("/project/{id}/version?"))
+                        .then_some(*body)
+                });
+                let response = match body {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        format!("http://{address}")
+    }
+
+    fn install(game_dir: &Path, pack_id: &str, pack_url: &str, version_id: &str) {
+        std::fs::create_dir_all(game_dir).unwrap();
+        let meta = serde_json::json!({
+            "format_version": crate::meta::FORMAT_VERSION,
+            "pack_id": pack_id,
+            "version_id": version_id,
+            "minecraft_version": "1.20.1",
+            "source": { "url": pack_url, "sha256": "00", "trust": { "type": "unsigned" } },
+            "files": [],
+            "metadata": null,
+        });
+        std::fs::write(game_dir.join(crate::meta::META_FILE_NAME), meta.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_against_mock_api() {
+        let api = mock_api(&[(
+            "fo",
+            r#"[
+                {"id": "old", "version_number": "5.0.0", "files": []},
+                {"id": "new", "version_number": "5.1.0", "files": [
+                    {"url": "https://cdn.modrinth.com/data/fo/versions/new/pack.mrpack", "primary": true, "filename": "pack.mrpack"},
+                    {"url": "https://cdn.modrinth.com/data/fo/versions/new/cosign-bundle.zip", "primary": false, "filename": "cosign-bundle.zip"}
+                ]}
+            ]"#,
+        )]);
+        let launcher_dir = tempfile::tempdir().unwrap();
+        let instances_dir = launcher_dir.path().join(instances::INSTANCES_DIR);
+        install(
+            launcher_dir.path(),
+            "fabulously-optimized",
+            "https://cdn.modrinth.com/data/fo/versions/old/pack.mrpack",
+            "5.0.0",
+        );
+        install(
+            &instances_dir.join("gone"),
+            "gone",
+            "https://cdn.modrinth.com/data/gone/versions/old/pack.mrpack",
+            "1.0.0",
+        );
+        install(
+            &instances_dir.join("elsewhere"),
+            "elsewhere",
+            "https://example.com/pack.mrpack",
+            "1.0.0",
+        );
+        let config = config::Config {
+            modrinth_api: api,
+            ..Default::default()
+        };
+
+        let report = check(&reqwest::Client::new(), &config, launcher_dir.path())
+            .await
+            .unwrap();
+
+        assert_eq!(report.updates.len(), 1);
+        let update = &report.updates[0];
+        assert_eq!(update.game_dir, launcher_dir.path());
+        assert_eq!(update.pack_id.as_deref(), Some("fabulously-optimized"));
+        assert_eq!(update.project_id, "fo");
+        assert_eq!(update.installed_version, "5.0.0");
+        assert_eq!(update.latest_version, "5.1.0");
+        assert_eq!(update.version_id, "new");
+        assert_eq!(
+            update.url,
+            "https://cdn.modrinth.com/data/fo/versions/new/pack.mrpack"
+        );
+        assert_eq!(
+            update.cosign_bundle_url.as_deref(),
+            Some("https://cdn.modrinth.com/data/fo/versions/new/cosign-bundle.zip")
+        );
+        assert_eq!(report.failed.len(), 1);
+        let failed = &report.failed[0];
+        assert_eq!(failed.game_dir, instances_dir.join("gone"));
+        assert_eq!(failed.pack_id.as_deref(), Some("gone"));
+        assert!(failed.error.contains("gone"), "{}", failed.error);
+    }
+}
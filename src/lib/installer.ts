@@ -7,6 +7,41 @@ function blobToDataURL(blob: Blob): Promise<string> {
 	});
 }
 
+export type ProgressStage =
+	| { stage: 'verify_files' }
+	| { stage: 'clean_old' }
+	| { stage: 'load_pack' }
+	| { stage: 'download_files'; fileCount: number; totalBytes: number }
+	| { stage: 'download_file'; index: number; path: string }
+	| { stage: 'extract_overrides' }
+	| { stage: 'check_mods' }
+	| { stage: 'install_loader' }
+	| { stage: 'install_java' }
+	| { stage: 'add_profile' };
+
+export type ProgressStatus =
+	| { status: 'start' }
+	| { status: 'progress'; bytesDone: number; bytesTotal: number; bytesPerSecond?: number }
+	| { status: 'complete' }
+	| { status: 'warning'; message: string }
+	| { status: 'error'; message: string }
+	| { status: 'cancelled' };
+
+/** Payload of `install:progress` events, see `src-tauri/src/progress.rs`. */
+export type ProgressEvent = { schemaVersion: 1 } & ProgressStage & ProgressStatus;
+
+/** What to do with jars in `mods/` that aren't part of the pack; defaults to `keep`. */
+export type UnmanagedModAction = 'keep' | 'disable_conflicting' | 'disable_all';
+
+/**
+ * Existing launcher profiles are updated in place, keeping the user's settings.
+ * New profiles get a heap size from `get_memory_recommendation` and the `jvm_args` string from `extra_metadata`;
+ * `apply_jvm_args` or `max_memory_mb` also apply them to an existing profile.
+ * `max_memory_mb` must be between the recommendation's `minimumMb` and `totalMb`.
+ * With `use_system_java`, the profile's `javaDir` is set to the runtime `find_java_runtimes` selects.
+ * With `download_java`, it is set to a runtime downloaded from Mojang, if no installed one was selected.
+ * With `instance_name`, the pack is installed as a separate instance with its own profile and game directory.
+ */
 export async function install_mrpack(
 	url: string,
 	pack_id: string,
@@ -14,7 +49,13 @@ export async function install_mrpack(
 	pack_name: string,
 	profile_dir: string | undefined,
 	extra_metadata: unknown,
-	cosign_bundle_url: string
+	cosign_bundle_url: string | undefined,
+	unmanaged_mods?: UnmanagedModAction,
+	apply_jvm_args?: boolean,
+	max_memory_mb?: number,
+	use_system_java?: boolean,
+	download_java?: boolean,
+	instance_name?: string
 ): Promise<void> {
 	await invoke('install_mrpack', {
 		url: url,
@@ -23,10 +64,82 @@ export async function install_mrpack(
 		packName: pack_name,
 		profileDir: profile_dir,
 		extraMetadata: extra_metadata,
-		cosignBundleUrl: cosign_bundle_url
+		cosignBundleUrl: cosign_bundle_url,
+		unmanagedMods: unmanaged_mods,
+		applyJvmArgs: apply_jvm_args,
+		maxMemoryMb: max_memory_mb,
+		useSystemJava: use_system_java,
+		downloadJava: download_java,
+		instanceName: instance_name
 	});
 }
 
+export interface MemoryRecommendation {
+	totalMb: number | null;
+	minimumMb: number;
+	recommendedMb: number;
+	/** The pack's minimum leaves less than half of the system's memory free. */
+	lowMemory: boolean;
+}
+
+/** `extra_metadata` may contain `memory: { minimum_mb, recommended_mb }`. */
+export async function get_memory_recommendation(
+	extra_metadata: unknown
+): Promise<MemoryRecommendation> {
+	return await invoke('get_memory_recommendation', { extraMetadata: extra_metadata });
+}
+
+export interface JavaRuntime {
+	path: string;
+	version: string;
+	majorVersion: number;
+}
+
+export interface JavaReport {
+	requiredMajorVersion: number | null;
+	runtimes: JavaRuntime[];
+	selected: JavaRuntime | null;
+}
+
+export async function find_java_runtimes(
+	minecraft_version: string | undefined
+): Promise<JavaReport> {
+	return await invoke('find_java_runtimes', { minecraftVersion: minecraft_version });
+}
+
+/** Returns the path of the `java` executable. */
+export async function install_java_runtime(minecraft_version: string): Promise<string> {
+	return await invoke('install_java_runtime', { minecraftVersion: minecraft_version });
+}
+
+export interface InstanceInfo {
+	instance: string | null;
+	packId: string | null;
+	packName: string | null;
+	profileId: string | null;
+	versionId: string | null;
+	minecraftVersion: string | null;
+	gameDir: string;
+}
+
+/** Scans the default game directory, the instances directory and every launcher profile's `gameDir` for installations. */
+export async function list_instances(pack_id: string | undefined): Promise<InstanceInfo[]> {
+	return await invoke('list_instances', { packId: pack_id });
+}
+
+export async function cancel_install(): Promise<boolean> {
+	return await invoke('cancel_install');
+}
+
+export async function get_bandwidth_limit(): Promise<number | null> {
+	return await invoke('get_bandwidth_limit');
+}
+
+/** Caps the combined download speed in bytes per second; `undefined` removes the cap. */
+export async function set_bandwidth_limit(bytes_per_second: number | undefined): Promise<void> {
+	await invoke('set_bandwidth_limit', { bytesPerSecond: bytes_per_second });
+}
+
 export async function get_installed_metadata(profile_dir: string | undefined): Promise<unknown> {
 	return await invoke('get_installed_metadata', {
 		profileDir: profile_dir
@@ -40,3 +153,74 @@ export async function show_profile_dir_selector(): Promise<string | null> {
 export async function is_launcher_installed(): Promise<boolean> {
 	return await invoke('is_launcher_installed');
 }
+
+export interface IntegrityReport {
+	missing: string[];
+	modified: string[];
+	extra: string[];
+}
+
+export async function verify_installation(
+	profile_dir: string | undefined
+): Promise<IntegrityReport> {
+	return await invoke('verify_installation', {
+		profileDir: profile_dir
+	});
+}
+
+export interface RepairReport {
+	redownloadedFiles: string[];
+	restoredOverrides: string[];
+	reinstalledLoader: boolean;
+	recreatedProfile: boolean;
+}
+
+export async function repair_installation(profile_dir: string | undefined): Promise<RepairReport> {
+	return await invoke('repair_installation', {
+		profileDir: profile_dir
+	});
+}
+
+export interface UnmanagedMod {
+	path: string;
+	modId: string | null;
+	name: string | null;
+	version: string | null;
+	conflictsWith: string[];
+}
+
+export async function list_unmanaged_mods(profile_dir: string | undefined): Promise<UnmanagedMod[]> {
+	return await invoke('list_unmanaged_mods', {
+		profileDir: profile_dir
+	});
+}
+
+export interface UpdateInfo {
+	gameDir: string;
+	instance: string | null;
+	packId: string | null;
+	projectId: string;
+	minecraftVersion: string;
+	installedVersion: string;
+	latestVersion: string;
+	versionId: string;
+	url: string;
+	cosignBundleUrl: string | null;
+}
+
+export interface FailedCheck {
+	gameDir: string;
+	instance: string | null;
+	packId: string | null;
+	error: string;
+}
+
+export interface UpdateReport {
+	updates: UpdateInfo[];
+	failed: FailedCheck[];
+}
+
+/** Checks every installation from Modrinth for a newer version for the same Minecraft version. */
+export async function check_for_updates(): Promise<UpdateReport> {
+	return await invoke('check_for_updates');
+}